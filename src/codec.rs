@@ -0,0 +1,46 @@
+//! Pluggable text formats for the serde store file.
+
+use std::collections::HashMap;
+
+use crate::entry::Entry;
+use crate::error::TinyKVError;
+use crate::store::TinyKV;
+
+/// Converts the store's entries to and from the text written to disk.
+///
+/// Set one with `TinyKV::with_codec`; `PrettyJsonCodec` is used by default.
+pub trait Codec: Send + Sync {
+    /// Renders every entry (keys include any namespace prefix) as file text.
+    fn serialize(&self, data: &HashMap<String, Entry>) -> Result<String, TinyKVError>;
+
+    /// Parses file text produced by `serialize` back into entries.
+    fn deserialize(&self, contents: &str) -> Result<HashMap<String, Entry>, TinyKVError>;
+}
+
+/// Indented JSON, the default format.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PrettyJsonCodec;
+
+impl Codec for PrettyJsonCodec {
+    fn serialize(&self, data: &HashMap<String, Entry>) -> Result<String, TinyKVError> {
+        serde_json::to_string_pretty(data).map_err(Into::into)
+    }
+
+    fn deserialize(&self, contents: &str) -> Result<HashMap<String, Entry>, TinyKVError> {
+        TinyKV::deserialize_data(contents)
+    }
+}
+
+/// Single-line JSON without whitespace, for smaller files.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompactJsonCodec;
+
+impl Codec for CompactJsonCodec {
+    fn serialize(&self, data: &HashMap<String, Entry>) -> Result<String, TinyKVError> {
+        serde_json::to_string(data).map_err(Into::into)
+    }
+
+    fn deserialize(&self, contents: &str) -> Result<HashMap<String, Entry>, TinyKVError> {
+        TinyKV::deserialize_data(contents)
+    }
+}
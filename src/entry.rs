@@ -3,6 +3,12 @@
 #[cfg(not(feature = "std"))]
 use alloc::string::String;
 
+#[cfg(all(not(feature = "std"), feature = "nanoserde"))]
+use alloc::string::ToString;
+
+#[cfg(any(feature = "std", feature = "nanoserde"))]
+use crate::error::TinyKVError;
+
 // Conditional imports based on feature flags
 #[cfg(feature = "nanoserde")]
 use nanoserde::{DeJson, SerJson};
@@ -10,27 +16,208 @@ use nanoserde::{DeJson, SerJson};
 #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
 use serde::{Deserialize, Serialize};
 
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+use std::borrow::Cow;
+
+#[cfg(all(not(feature = "nanoserde"), feature = "compression"))]
+use std::io::{Read, Write};
+
+#[cfg(all(not(feature = "nanoserde"), feature = "compression"))]
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+
 // Entry struct with conditional serialization
+/// A stored value together with its optional expiry, as persisted in the store file.
 #[cfg(feature = "nanoserde")]
 #[derive(DeJson, SerJson, Debug, Clone)]
 pub struct Entry {
     pub value: String, // nanoserde stores as JSON string
     #[nserde(default)]
     pub expires_at: Option<u64>, // UNIX timestamp (seconds)
+    #[nserde(default)]
+    pub modified_at: Option<u64>, // UNIX timestamp (seconds) of the last write
+    #[nserde(default)]
+    pub created_at: Option<u64>, // UNIX timestamp (seconds) of the first write
+    #[nserde(default)]
+    pub accessed_at: Option<u64>, // UNIX timestamp (seconds) of the last read
+    #[nserde(default)]
+    pub hits: u64, // Number of successful reads
+    #[nserde(skip)]
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    pub(crate) inserted_seq: u64, // In-memory write order for FIFO eviction, not persisted
+    #[nserde(skip)]
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    pub(crate) used_seq: u64, // In-memory order of the last write or read for LRU eviction
 }
 
+/// A stored value together with its optional expiry, as persisted in the store file.
 #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Entry {
     pub value: serde_json::Value,
     #[serde(default)]
     pub expires_at: Option<u64>, // UNIX timestamp (seconds)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_at: Option<u64>, // UNIX timestamp (seconds) of the last write
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<u64>, // UNIX timestamp (seconds) of the first write
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accessed_at: Option<u64>, // UNIX timestamp (seconds) of the last read
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub hits: u64, // Number of successful reads
+    #[serde(default, skip_serializing_if = "core::ops::Not::not")]
+    pub compressed: bool, // `value` holds base64-encoded gzip of the JSON value
+    #[serde(skip)]
+    pub(crate) inserted_seq: u64, // In-memory write order for FIFO eviction, not persisted
+    #[serde(skip)]
+    pub(crate) used_seq: u64, // In-memory order of the last write or read for LRU eviction
+}
+
+/// Helper for `skip_serializing_if`, so entries that were never read stay compact.
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+fn is_zero(n: &u64) -> bool {
+    *n == 0
 }
 
 // For no_std without nanoserde, we use a simpler approach
+/// A stored value together with its optional expiry.
 #[cfg(all(not(feature = "nanoserde"), not(feature = "std")))]
 #[derive(Debug, Clone)]
 pub struct Entry {
     pub value: String,           // Simple string storage for no_std
     pub expires_at: Option<u64>, // UNIX timestamp (seconds)
 }
+
+/// Timing and usage metadata for a single entry, returned by `TinyKV::entry_metadata`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EntryMeta {
+    /// UNIX timestamp (seconds) of the first write, if recorded
+    pub created_at: Option<u64>,
+    /// UNIX timestamp (seconds) of the last write, if recorded
+    pub modified_at: Option<u64>,
+    /// UNIX timestamp (seconds) of the last read, if any
+    pub accessed_at: Option<u64>,
+    /// UNIX timestamp (seconds) after which the entry expires, if any
+    pub expires_at: Option<u64>,
+    /// Number of successful reads
+    pub hits: u64,
+}
+
+#[cfg(any(feature = "std", feature = "nanoserde"))]
+impl Entry {
+    /// Returns the timing and usage metadata of this entry.
+    pub fn metadata(&self) -> EntryMeta {
+        EntryMeta {
+            created_at: self.created_at,
+            modified_at: self.modified_at,
+            accessed_at: self.accessed_at,
+            expires_at: self.expires_at,
+            hits: self.hits,
+        }
+    }
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+impl Entry {
+    /// Creates an entry written at `now`, with no reads recorded yet.
+    pub(crate) fn new(value: serde_json::Value, expires_at: Option<u64>, now: Option<u64>) -> Self {
+        Self {
+            value,
+            expires_at,
+            modified_at: now,
+            created_at: now,
+            accessed_at: None,
+            hits: 0,
+            compressed: false,
+            inserted_seq: 0,
+            used_seq: 0,
+        }
+    }
+
+    /// Returns the stored value, decompressing it first if it was stored compressed.
+    /// Compressed values can only be read with the `compression` feature.
+    pub fn decoded_value(&self) -> Result<Cow<'_, serde_json::Value>, TinyKVError> {
+        if !self.compressed {
+            return Ok(Cow::Borrowed(&self.value));
+        }
+        self.decompress().map(Cow::Owned)
+    }
+
+    #[cfg(feature = "compression")]
+    fn decompress(&self) -> Result<serde_json::Value, TinyKVError> {
+        let encoded = self.value.as_str().ok_or_else(|| {
+            TinyKVError::Serialization("compressed value is not a string".to_string())
+        })?;
+        let bytes = BASE64
+            .decode(encoded)
+            .map_err(|e| TinyKVError::Serialization(e.to_string()))?;
+        let mut json = Vec::new();
+        flate2::read::GzDecoder::new(bytes.as_slice()).read_to_end(&mut json)?;
+        Ok(serde_json::from_slice(&json)?)
+    }
+
+    #[cfg(not(feature = "compression"))]
+    fn decompress(&self) -> Result<serde_json::Value, TinyKVError> {
+        Err(TinyKVError::Serialization(
+            "compressed values require the `compression` feature".to_string(),
+        ))
+    }
+
+    /// Compresses the value in place if its JSON is longer than `threshold` bytes.
+    /// The value is left plain if it is small or compression fails.
+    #[cfg(feature = "compression")]
+    pub(crate) fn compress_above(&mut self, threshold: usize) {
+        if self.compressed {
+            return;
+        }
+        let Ok(json) = serde_json::to_vec(&self.value) else {
+            return;
+        };
+        if json.len() <= threshold {
+            return;
+        }
+
+        let mut encoder =
+            flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        if let Ok(bytes) = encoder.write_all(&json).and_then(|()| encoder.finish()) {
+            self.value = serde_json::Value::String(BASE64.encode(bytes));
+            self.compressed = true;
+        }
+    }
+
+    /// Serializes this entry to JSON using the same envelope the store persists.
+    pub fn to_json(&self) -> Result<String, TinyKVError> {
+        serde_json::to_string(self).map_err(Into::into)
+    }
+
+    /// Parses an entry previously produced by `to_json`.
+    pub fn from_json(json: &str) -> Result<Entry, TinyKVError> {
+        serde_json::from_str(json).map_err(Into::into)
+    }
+}
+
+#[cfg(feature = "nanoserde")]
+impl Entry {
+    /// Creates an entry written at `now`, with no reads recorded yet.
+    pub(crate) fn new(value: String, expires_at: Option<u64>, now: Option<u64>) -> Self {
+        Self {
+            value,
+            expires_at,
+            modified_at: now,
+            created_at: now,
+            accessed_at: None,
+            hits: 0,
+            inserted_seq: 0,
+            used_seq: 0,
+        }
+    }
+
+    /// Serializes this entry to JSON using the same envelope the store persists.
+    pub fn to_json(&self) -> Result<String, TinyKVError> {
+        Ok(self.serialize_json())
+    }
+
+    /// Parses an entry previously produced by `to_json`.
+    pub fn from_json(json: &str) -> Result<Entry, TinyKVError> {
+        Entry::deserialize_json(json).map_err(|e| TinyKVError::Serialization(e.to_string()))
+    }
+}
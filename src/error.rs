@@ -20,6 +20,27 @@ pub enum TinyKVError {
     /// Feature not available in no_std mode
     #[cfg(not(feature = "std"))]
     NoStdUnsupported(String),
+    /// Value rejected by the configured value validator (holds the key)
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    ValidationFailed(String),
+    /// A live entry already occupies the key passed to `insert_new` (holds the key)
+    KeyExists(String),
+    /// No live entry for the key passed to `get_required` (holds the key)
+    KeyNotFound(String),
+    /// The key is reserved for store metadata such as the schema version (holds the key)
+    ReservedKey(String),
+    /// Wrong key or corrupted data when decrypting the store file (only available with encryption)
+    #[cfg(feature = "encryption")]
+    Decryption,
+    /// The store's file lock stayed taken past the lock timeout (only available with file-lock)
+    #[cfg(feature = "file-lock")]
+    Locked,
+    /// A mutation was attempted on a store opened with `open_read_only` (only available with std)
+    #[cfg(feature = "std")]
+    ReadOnly,
+    /// Absolute expiry timestamp is already in the past (holds the timestamp)
+    #[cfg(any(feature = "std", feature = "wasm"))]
+    ExpiryInPast(u64),
     /// Web storage related error (only available with wasm)
     #[cfg(feature = "wasm")]
     WebStorage(String),
@@ -39,6 +60,13 @@ impl From<serde_json::Error> for TinyKVError {
     }
 }
 
+#[cfg(feature = "csv")]
+impl From<csv::Error> for TinyKVError {
+    fn from(err: csv::Error) -> Self {
+        Self::Serialization(err.to_string())
+    }
+}
+
 impl core::fmt::Display for TinyKVError {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
@@ -49,6 +77,19 @@ impl core::fmt::Display for TinyKVError {
             Self::TimeError => write!(f, "Time error"),
             #[cfg(not(feature = "std"))]
             Self::NoStdUnsupported(msg) => write!(f, "Feature not available in no_std: {msg}"),
+            #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+            Self::ValidationFailed(key) => write!(f, "Validation failed for key: {key}"),
+            Self::KeyExists(key) => write!(f, "Key already exists: {key}"),
+            Self::KeyNotFound(key) => write!(f, "Key not found: {key}"),
+            Self::ReservedKey(key) => write!(f, "Key is reserved: {key}"),
+            #[cfg(feature = "encryption")]
+            Self::Decryption => write!(f, "Decryption failed: wrong key or corrupted file"),
+            #[cfg(feature = "file-lock")]
+            Self::Locked => write!(f, "Store file is locked by another process"),
+            #[cfg(feature = "std")]
+            Self::ReadOnly => write!(f, "Store is read-only"),
+            #[cfg(any(feature = "std", feature = "wasm"))]
+            Self::ExpiryInPast(ts) => write!(f, "Expiry timestamp is in the past: {ts}"),
             #[cfg(feature = "wasm")]
             Self::WebStorage(msg) => write!(f, "Web storage error: {msg}"),
         }
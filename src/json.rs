@@ -0,0 +1,240 @@
+//! Minimal JSON reading and writing for the `no_std` store format without `nanoserde`.
+//!
+//! Only the shape written by the `no_std` serializer is understood:
+//! `{"key":{"value":"...","expires_at":null|number}}`.
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+};
+
+use crate::entry::Entry;
+use crate::error::TinyKVError;
+
+/// Renders entries as a compact JSON object, escaping every key and value.
+pub(crate) fn write_store(data: &BTreeMap<String, Entry>) -> String {
+    let mut out = String::from("{");
+    for (i, (key, entry)) in data.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        write_string(&mut out, key);
+        out.push_str(r#":{"value":"#);
+        write_string(&mut out, &entry.value);
+        out.push_str(r#","expires_at":"#);
+        match entry.expires_at {
+            Some(exp) => out.push_str(&exp.to_string()),
+            None => out.push_str("null"),
+        }
+        out.push('}');
+    }
+    out.push('}');
+    out
+}
+
+/// Appends `s` to `out` as a quoted JSON string, escaping quotes, backslashes and
+/// control characters.
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Parses a store object into its entries. Unknown fields inside an entry are rejected.
+pub(crate) fn parse_store(contents: &str) -> Result<BTreeMap<String, Entry>, TinyKVError> {
+    let mut parser = Parser {
+        src: contents,
+        pos: 0,
+    };
+    let mut data = BTreeMap::new();
+    parser.parse_object(|parser, key| {
+        let entry = parser.parse_entry()?;
+        data.insert(key, entry);
+        Ok(())
+    })?;
+
+    parser.skip_whitespace();
+    if parser.pos != parser.src.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    Ok(data)
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, msg: &str) -> TinyKVError {
+        TinyKVError::Serialization(format!("invalid store data at byte {}: {}", self.pos, msg))
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), TinyKVError> {
+        self.skip_whitespace();
+        if self.peek() != Some(byte) {
+            return Err(self.error(&format!("expected `{}`", byte as char)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Walks a `{"name": ...}` object, handing each member name to `member`, which must
+    /// consume the member's value.
+    fn parse_object<F>(&mut self, mut member: F) -> Result<(), TinyKVError>
+    where
+        F: FnMut(&mut Self, String) -> Result<(), TinyKVError>,
+    {
+        self.expect(b'{')?;
+        self.skip_whitespace();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(());
+        }
+
+        loop {
+            let name = self.parse_string()?;
+            self.expect(b':')?;
+            member(self, name)?;
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                _ => return Err(self.error("expected `,` or `}`")),
+            }
+        }
+    }
+
+    fn parse_entry(&mut self) -> Result<Entry, TinyKVError> {
+        let mut value = None;
+        let mut expires_at = None;
+        self.parse_object(|parser, field| match field.as_str() {
+            "value" => {
+                value = Some(parser.parse_string()?);
+                Ok(())
+            }
+            "expires_at" => {
+                expires_at = parser.parse_optional_u64()?;
+                Ok(())
+            }
+            _ => Err(parser.error(&format!("unknown field `{}`", field))),
+        })?;
+
+        match value {
+            Some(value) => Ok(Entry { value, expires_at }),
+            None => Err(self.error("missing field `value`")),
+        }
+    }
+
+    fn parse_optional_u64(&mut self) -> Result<Option<u64>, TinyKVError> {
+        self.skip_whitespace();
+        if self.src[self.pos..].starts_with("null") {
+            self.pos += 4;
+            return Ok(None);
+        }
+
+        let start = self.pos;
+        while matches!(self.peek(), Some(b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        self.src[start..self.pos]
+            .parse()
+            .map(Some)
+            .map_err(|_| self.error("expected `null` or an unsigned integer"))
+    }
+
+    fn parse_string(&mut self) -> Result<String, TinyKVError> {
+        self.expect(b'"')?;
+        let mut out = String::new();
+        loop {
+            // Scanning stops only at ASCII bytes, so `start..pos` is always a char boundary.
+            let start = self.pos;
+            while !matches!(self.peek(), Some(b'"' | b'\\') | None) {
+                self.pos += 1;
+            }
+            out.push_str(&self.src[start..self.pos]);
+
+            match self.peek() {
+                Some(b'"') => {
+                    self.pos += 1;
+                    return Ok(out);
+                }
+                Some(b'\\') => {
+                    self.pos += 1;
+                    out.push(self.parse_escape()?);
+                }
+                _ => return Err(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<char, TinyKVError> {
+        let escaped = self.peek().ok_or_else(|| self.error("unterminated escape"))?;
+        self.pos += 1;
+        match escaped {
+            b'"' => Ok('"'),
+            b'\\' => Ok('\\'),
+            b'/' => Ok('/'),
+            b'b' => Ok('\u{8}'),
+            b'f' => Ok('\u{c}'),
+            b'n' => Ok('\n'),
+            b'r' => Ok('\r'),
+            b't' => Ok('\t'),
+            b'u' => {
+                let high = self.parse_hex4()?;
+                let code = if (0xD800..0xDC00).contains(&high) {
+                    if !self.src[self.pos..].starts_with("\\u") {
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    self.pos += 2;
+                    let low = self.parse_hex4()?;
+                    if !(0xDC00..0xE000).contains(&low) {
+                        return Err(self.error("unpaired surrogate"));
+                    }
+                    0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+                } else {
+                    high
+                };
+                char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+            }
+            _ => Err(self.error("invalid escape")),
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, TinyKVError> {
+        let digits = self
+            .src
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(|| self.error("truncated unicode escape"))?;
+        if !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(self.error("invalid unicode escape"));
+        }
+        self.pos += 4;
+        Ok(u32::from_str_radix(digits, 16).unwrap_or_default())
+    }
+}
@@ -0,0 +1,45 @@
+//! Store key type for the `*_by` accessors.
+
+#[cfg(not(feature = "std"))]
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+
+/// A key accepted by `set_by`, `get_by` and `remove_by`, encoded as the string stored on disk.
+///
+/// Strings are used as-is, integers are written in decimal and byte slices as lowercase hex,
+/// so `42u64` and `"42"` name the same entry.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StoreKey(String);
+
+impl StoreKey {
+    /// Returns the encoded key as stored in the store.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for StoreKey {
+    fn from(key: &str) -> Self {
+        Self(key.to_string())
+    }
+}
+
+impl From<String> for StoreKey {
+    fn from(key: String) -> Self {
+        Self(key)
+    }
+}
+
+impl From<u64> for StoreKey {
+    fn from(key: u64) -> Self {
+        Self(key.to_string())
+    }
+}
+
+impl From<&[u8]> for StoreKey {
+    fn from(key: &[u8]) -> Self {
+        Self(key.iter().map(|byte| format!("{:02x}", byte)).collect())
+    }
+}
@@ -16,6 +16,15 @@
 //! - `default`: Uses `serde` for serialization (maximum compatibility) and `std`
 //! - `nanoserde`: Uses `nanoserde` for minimal binary size and faster compilation
 //! - `std`: Enables `std` library (enabled by default)
+//! - `logging`: Emits `log` records for set, get misses, expiry and save
+//! - `rand`: Enables `set_with_ttl_jitter` for randomized expiry times
+//! - `csv`: Enables `export_csv` and `import_csv` for two-column string data,
+//!   and `export_csv_entries` / `import_csv_entries` for JSON values with expiry
+//! - `tokio`: Enables the async `expiry_notification`
+//! - `compression`: Enables `with_compression` for gzipped store files and
+//!   `with_compress_above` for gzip-compressed large values
+//! - `encryption`: Enables `with_encryption_key` for ChaCha20-Poly1305 encrypted store files
+//! - `file-lock`: Locks a sidecar `.lock` file during `save`/`reload` and adds `open_locked`
 //!
 //! ## Example
 //!
@@ -24,7 +33,9 @@
 //!
 //! # #[cfg(feature = "std")]
 //! fn main() -> Result<(), Box<dyn std::error::Error>> {
-//!     let mut kv = TinyKV::open("mydata.json")?.with_auto_save();
+//! #   let dir = tempfile::tempdir()?;
+//! #   let path = dir.path().join("mydata.json");
+//!     let mut kv = TinyKV::open(path)?.with_auto_save();
 //!     kv.set("username", "hasan".to_string())?;
 //!     kv.set_with_ttl("session_token", "abc123".to_string(), 60)?; // 60 seconds TTL
 //!     let user: Option<String> = kv.get("username")?;
@@ -48,8 +59,13 @@
 extern crate alloc;
 
 // Module declarations
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+mod codec;
 mod entry;
 mod error;
+#[cfg(all(not(feature = "nanoserde"), not(feature = "std")))]
+mod json;
+mod key;
 mod store;
 
 // WASM bindings module
@@ -60,8 +76,19 @@ mod wasm;
 mod tests;
 
 // Public exports - only the essential ones from original
+pub use entry::{Entry, EntryMeta};
 pub use error::TinyKVError;
-pub use store::TinyKV;
+pub use key::StoreKey;
+pub use store::{GcStats, Snapshot, TinyKV, Ttl};
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+pub use codec::{Codec, CompactJsonCodec, PrettyJsonCodec};
+
+#[cfg(feature = "std")]
+pub use store::{
+    BackupStrategy, ChangeKind, CheckpointId, EvictionPolicy, LockGuard, MergeStrategy,
+    NamespacedView, Op, TypedKey,
+};
 
 // Re-export WASM types for convenience
 #[cfg(feature = "wasm")]
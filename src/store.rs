@@ -23,13 +23,31 @@ use std::collections::HashMap;
 use std::fs;
 
 #[cfg(feature = "std")]
-use std::io::{self, ErrorKind};
+use std::marker::PhantomData;
+
+#[cfg(feature = "std")]
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+
+#[cfg(feature = "std")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+use std::{any::Any, borrow::Cow, sync::Arc};
+
+#[cfg(feature = "std")]
+use std::io::{self, ErrorKind, Write};
+
+#[cfg(any(feature = "compression", feature = "csv"))]
+use std::io::Read;
 
 #[cfg(feature = "std")]
 use std::path::{Path, PathBuf};
 
 #[cfg(feature = "std")]
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 #[cfg(feature = "wasm")]
 use crate::wasm;
@@ -41,8 +59,58 @@ use nanoserde::{DeJson, SerJson};
 #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
 use serde::{Deserialize, Serialize};
 
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+use crate::codec::{Codec, CompactJsonCodec, PrettyJsonCodec};
 use crate::entry::Entry;
 use crate::error::TinyKVError;
+#[cfg(all(not(feature = "nanoserde"), not(feature = "std")))]
+use crate::json;
+use crate::key::StoreKey;
+
+/// Reserved key holding the schema version stamped by `with_migration`.
+/// Hidden from `keys()`, `len()` and the other key listings.
+const VERSION_KEY: &str = "__tinykv:version";
+
+/// How long `save`, `reload` and `open_locked` wait for the file lock by default.
+#[cfg(feature = "file-lock")]
+const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Emits a `log` record when the `logging` feature is enabled; compiles to nothing otherwise.
+#[cfg_attr(not(any(feature = "std", feature = "nanoserde")), allow(unused_macros))]
+macro_rules! log_op {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "logging")]
+        log::$level!($($arg)+);
+    };
+}
+
+/// Transformation applied to the serialized store text on save or load.
+#[cfg(feature = "std")]
+type SerializationHook = Box<dyn Fn(String) -> String + Send + Sync>;
+
+/// Callback invoked with the entry count when the soft limit is crossed.
+#[cfg(feature = "std")]
+type SoftLimitCallback = Box<dyn Fn(usize) + Send + Sync>;
+
+/// Supplies a replacement value for a key whose stored value fails to parse.
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+type ParseErrorRecovery = Box<dyn Fn(&str) -> serde_json::Value + Send + Sync>;
+
+/// Callback invoked with an operation and how long it took.
+#[cfg(feature = "std")]
+type Observer = Box<dyn Fn(Op, Duration) + Send + Sync>;
+
+/// Callback registered with `on_change`, invoked with the key and kind of each mutation.
+#[cfg(feature = "std")]
+type ChangeCallback = Box<dyn FnMut(&str, ChangeKind) + Send + Sync>;
+
+/// Callback registered with `on_evict`, invoked with each key dropped by capacity eviction.
+#[cfg(feature = "std")]
+type EvictCallback = Box<dyn FnMut(String) + Send + Sync>;
+
+/// Deserialized value cached by `get_cached`, tagged with the hash of the JSON it came from.
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+type CachedValue = (u64, Arc<dyn Any + Send + Sync>);
 
 /// A simple persistent key-value store with TTL and auto-save.
 ///
@@ -61,6 +129,90 @@ pub struct TinyKV {
     data: BTreeMap<String, Entry>,
     auto_save: bool,
     backup_enabled: bool,
+    #[cfg(feature = "std")]
+    create_dirs: bool,
+    #[cfg(feature = "std")]
+    backup_dir: Option<PathBuf>,
+    #[cfg(feature = "std")]
+    backup_strategy: BackupStrategy,
+    #[cfg(feature = "std")]
+    coalesce_window: Option<Duration>,
+    #[cfg(feature = "std")]
+    last_key_saves: HashMap<String, Instant>,
+    #[cfg(feature = "std")]
+    fsync_interval: Option<Duration>,
+    #[cfg(feature = "compression")]
+    compress_file: bool,
+    #[cfg(feature = "encryption")]
+    encryption_key: Option<[u8; 32]>,
+    #[cfg(feature = "file-lock")]
+    file_lock: Option<fs::File>,
+    #[cfg(feature = "file-lock")]
+    lock_timeout: Duration,
+    #[cfg(feature = "std")]
+    read_only: bool,
+    #[cfg(feature = "std")]
+    last_fsync: Mutex<Option<Instant>>,
+    #[cfg(feature = "std")]
+    append_log: bool,
+    #[cfg(feature = "std")]
+    suppress_writes: bool,
+    #[cfg(feature = "std")]
+    auto_purge_ratio: Option<f64>,
+    #[cfg(feature = "std")]
+    purge_on_load: bool,
+    #[cfg(feature = "logging")]
+    redact_log_keys: bool,
+    #[cfg(feature = "std")]
+    soft_limit: Option<(usize, SoftLimitCallback)>,
+    #[cfg(feature = "std")]
+    capacity: Option<usize>,
+    #[cfg(feature = "std")]
+    last_saved_hash: Mutex<Option<u64>>,
+    #[cfg(feature = "std")]
+    observer: Option<Observer>,
+    #[cfg(feature = "std")]
+    change_callbacks: Vec<ChangeCallback>,
+    #[cfg(feature = "std")]
+    evict_callback: Option<EvictCallback>,
+    #[cfg(feature = "std")]
+    access_seq: u64,
+    #[cfg(feature = "std")]
+    eviction_policy: EvictionPolicy,
+    #[cfg(feature = "std")]
+    soft_limit_exceeded: bool,
+    #[cfg(feature = "std")]
+    ttl_entries: usize,
+    #[cfg(feature = "std")]
+    next_ttl_expiry: u64,
+    #[cfg(feature = "std")]
+    known_expired: usize,
+    #[cfg(feature = "std")]
+    pre_save: Option<SerializationHook>,
+    #[cfg(feature = "std")]
+    post_load: Option<SerializationHook>,
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    value_validator: Option<fn(&serde_json::Value) -> bool>,
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    export_cursor: Option<String>,
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    read_cache: Option<HashMap<String, CachedValue>>,
+    #[cfg(all(not(feature = "nanoserde"), feature = "compression"))]
+    compress_above: Option<usize>,
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    parse_error_recovery: Option<ParseErrorRecovery>,
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    codec: Box<dyn Codec>,
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    custom_codec: bool,
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    strict_load: bool,
+    #[cfg(any(feature = "std", feature = "wasm"))]
+    may_have_ttl: bool,
+    #[cfg(any(feature = "std", feature = "wasm"))]
+    opportunistic_purge: bool,
+    #[cfg(any(feature = "std", feature = "wasm"))]
+    purge_queue: Vec<String>,
 }
 
 impl TinyKV {
@@ -69,46 +221,109 @@ impl TinyKV {
     #[cfg(feature = "std")]
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, TinyKVError> {
         let path_buf = path.as_ref().to_path_buf();
-        let data = match fs::read_to_string(&path_buf) {
+        let data = match Self::read_file_text(&path_buf) {
             Ok(contents) => Self::deserialize_data(&contents)?,
             Err(e) if e.kind() == ErrorKind::NotFound => HashMap::new(),
             Err(e) => return Err(TinyKVError::Io(e)),
         };
 
-        Ok(Self {
-            path: path_buf,
-            #[cfg(feature = "wasm")]
-            web_prefix: String::new(),
-            namespace: String::new(),
-            data,
-            auto_save: false,
-            backup_enabled: false,
-        })
+        let mut kv = Self::new();
+        kv.path = path_buf;
+        kv.data = data;
+        kv.refresh_ttl_flag();
+        Ok(kv)
+    }
+
+    /// Like `open`, but rejects a file in which a key appears more than once
+    /// instead of silently keeping the last occurrence.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn open_strict<P: AsRef<Path>>(path: P) -> Result<Self, TinyKVError> {
+        Self::new().with_strict_load().load_from(path)
+    }
+
+    /// Like `open`, but takes an exclusive lock on the sidecar `.lock` file first and holds
+    /// it until the store is dropped, so no other process can save or reload meanwhile.
+    /// Fails with `TinyKVError::Locked` if the lock isn't free within 5 seconds.
+    /// Only available with `file-lock` feature.
+    #[cfg(feature = "file-lock")]
+    pub fn open_locked<P: AsRef<Path>>(path: P) -> Result<Self, TinyKVError> {
+        let lock = Self::lock_file(&path.as_ref().with_extension("lock"), DEFAULT_LOCK_TIMEOUT)?;
+        let mut kv = Self::open(path)?;
+        kv.file_lock = Some(lock);
+        Ok(kv)
+    }
+
+    /// Like `open`, but every mutating call (`set`, `remove`, `clear`, `save`, ...)
+    /// fails with `TinyKVError::ReadOnly`, and `get` leaves expired keys in place
+    /// instead of purging them. Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn open_read_only<P: AsRef<Path>>(path: P) -> Result<Self, TinyKVError> {
+        let mut kv = Self::open(path)?;
+        kv.read_only = true;
+        Ok(kv)
+    }
+
+    /// Helper function to reject mutations on a store opened with `open_read_only`.
+    #[cfg(feature = "std")]
+    fn ensure_writable(&self) -> Result<(), TinyKVError> {
+        if self.read_only {
+            return Err(TinyKVError::ReadOnly);
+        }
+        Ok(())
+    }
+
+    /// Points this store at `path` and loads it, applying any configured
+    /// `with_post_load` hook. Use this instead of `open` when the file on disk
+    /// was written through a `with_pre_save` hook.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn load_from<P: AsRef<Path>>(mut self, path: P) -> Result<Self, TinyKVError> {
+        self.path = path.as_ref().to_path_buf();
+        self.reload()?;
+        Ok(self)
+    }
+
+    /// Open or create an append-only log store at the given file path.
+    /// Each `set` appends a single JSON record line instead of rewriting the file;
+    /// opening replays all records, later ones winning. A full `save` compacts the
+    /// log to one record per live key. Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn open_append_log<P: AsRef<Path>>(path: P) -> Result<Self, TinyKVError> {
+        let mut kv = Self::new();
+        kv.path = path.as_ref().to_path_buf();
+        kv.append_log = true;
+        kv.reload()?;
+        Ok(kv)
     }
 
     /// Create TinyKV store using browser localStorage.
     /// Only available with `wasm` feature.
     #[cfg(feature = "wasm")]
     pub fn open_localstorage(prefix: &str) -> Result<Self, TinyKVError> {
-        let mut kv = Self {
-            #[cfg(feature = "std")]
-            path: PathBuf::new(),
-            web_prefix: prefix.to_string(),
-            namespace: String::new(),
-            data: HashMap::new(),
-            auto_save: false,
-            backup_enabled: false,
-        };
-
+        let mut kv = Self::new();
+        kv.web_prefix = prefix.to_string();
         kv.web_load()?;
         Ok(kv)
     }
 
+    /// Returns whether browser localStorage is usable (write, read back, delete).
+    /// Only available with `wasm` feature.
+    #[cfg(feature = "wasm")]
+    pub fn localstorage_available() -> bool {
+        wasm::localstorage_available()
+    }
+
     /// Create TinyKV store with automatic backend selection.
     /// Tries localStorage first, falls back to Error.
     /// Only available with `wasm` feature.
     #[cfg(feature = "wasm")]
     pub fn open_web_auto(prefix: &str) -> Result<Self, TinyKVError> {
+        if !Self::localstorage_available() {
+            return Err(TinyKVError::WebStorage(
+                "No usable web storage (localStorage unavailable or disabled)".into(),
+            ));
+        }
+
         // Try localStorage first
         match Self::open_localstorage(prefix) {
             Ok(kv) => Ok(kv),
@@ -133,6 +348,90 @@ impl TinyKV {
             data: BTreeMap::new(),
             auto_save: false,
             backup_enabled: false,
+            #[cfg(feature = "std")]
+            create_dirs: false,
+            #[cfg(feature = "std")]
+            backup_dir: None,
+            #[cfg(feature = "std")]
+            backup_strategy: BackupStrategy::Overwrite,
+            #[cfg(feature = "std")]
+            coalesce_window: None,
+            #[cfg(feature = "std")]
+            last_key_saves: HashMap::new(),
+            #[cfg(feature = "std")]
+            fsync_interval: None,
+            #[cfg(feature = "compression")]
+            compress_file: false,
+            #[cfg(feature = "encryption")]
+            encryption_key: None,
+            #[cfg(feature = "file-lock")]
+            file_lock: None,
+            #[cfg(feature = "file-lock")]
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            #[cfg(feature = "std")]
+            read_only: false,
+            #[cfg(feature = "std")]
+            last_fsync: Mutex::new(None),
+            #[cfg(feature = "std")]
+            append_log: false,
+            #[cfg(feature = "std")]
+            suppress_writes: false,
+            #[cfg(feature = "std")]
+            auto_purge_ratio: None,
+            #[cfg(feature = "std")]
+            purge_on_load: false,
+            #[cfg(feature = "logging")]
+            redact_log_keys: false,
+            #[cfg(feature = "std")]
+            soft_limit: None,
+            #[cfg(feature = "std")]
+            capacity: None,
+            #[cfg(feature = "std")]
+            last_saved_hash: Mutex::new(None),
+            #[cfg(feature = "std")]
+            observer: None,
+            #[cfg(feature = "std")]
+            change_callbacks: Vec::new(),
+            #[cfg(feature = "std")]
+            evict_callback: None,
+            #[cfg(feature = "std")]
+            access_seq: 0,
+            #[cfg(feature = "std")]
+            eviction_policy: EvictionPolicy::Lru,
+            #[cfg(feature = "std")]
+            soft_limit_exceeded: false,
+            #[cfg(feature = "std")]
+            ttl_entries: 0,
+            #[cfg(feature = "std")]
+            next_ttl_expiry: 0,
+            #[cfg(feature = "std")]
+            known_expired: 0,
+            #[cfg(feature = "std")]
+            pre_save: None,
+            #[cfg(feature = "std")]
+            post_load: None,
+            #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+            value_validator: None,
+            #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+            export_cursor: None,
+            #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+            read_cache: None,
+            #[cfg(all(not(feature = "nanoserde"), feature = "compression"))]
+            compress_above: None,
+            #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+            parse_error_recovery: None,
+            #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+            codec: Box::new(PrettyJsonCodec),
+            #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+            custom_codec: false,
+            #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+            strict_load: false,
+            #[cfg(any(feature = "std", feature = "wasm"))]
+            may_have_ttl: false,
+            #[cfg(any(feature = "std", feature = "wasm"))]
+            opportunistic_purge: false,
+            #[cfg(any(feature = "std", feature = "wasm"))]
+            purge_queue: Vec::new(),
         }
     }
 
@@ -140,16 +439,30 @@ impl TinyKV {
     /// Available in both `std` and `no_std` modes.
     pub fn from_data(data: &str) -> Result<Self, TinyKVError> {
         let data = Self::deserialize_data(data)?;
-        Ok(Self {
-            #[cfg(feature = "std")]
-            path: PathBuf::new(),
-            #[cfg(feature = "wasm")]
-            web_prefix: String::new(),
-            namespace: String::new(),
-            data,
-            auto_save: false,
-            backup_enabled: false,
-        })
+        let mut kv = Self::new();
+        kv.data = data;
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        kv.refresh_ttl_flag();
+        Ok(kv)
+    }
+
+    /// Like `from_data`, but rejects input in which a key appears more than once
+    /// instead of silently keeping the last occurrence.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn from_data_strict(data: &str) -> Result<Self, TinyKVError> {
+        let data = Self::deserialize_data_strict(data)?;
+        let mut kv = Self::new();
+        kv.data = data;
+        kv.refresh_ttl_flag();
+        Ok(kv)
+    }
+
+    /// Create a TinyKV store from a UTF-8 byte slice, such as one embedded with
+    /// `include_bytes!`. Available in both `std` and `no_std` modes.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, TinyKVError> {
+        let data = core::str::from_utf8(data)
+            .map_err(|e| TinyKVError::Serialization(e.to_string()))?;
+        Self::from_data(data)
     }
 
     /// Serialize the store to a string.
@@ -158,6 +471,25 @@ impl TinyKV {
         self.serialize_data()
     }
 
+    /// Serializes only the current namespace's entries whose (namespace-stripped) key
+    /// passes `f`, in the same format as `to_data`, so the result loads with `from_data`.
+    /// Reserved keys such as the schema version stamp are never included.
+    #[cfg(any(feature = "std", feature = "nanoserde"))]
+    pub fn to_data_filtered<F: Fn(&str) -> bool>(&self, f: F) -> Result<String, TinyKVError> {
+        let mut data = self.data.clone();
+        data.retain(|key, _| {
+            key.starts_with(&self.namespace)
+                && !Self::is_reserved_key(key)
+                && f(&self.strip_namespace(key))
+        });
+
+        // Same encoding as `serialize_data`, including a custom codec.
+        #[cfg(feature = "nanoserde")]
+        return Ok(data.serialize_json());
+        #[cfg(not(feature = "nanoserde"))]
+        self.codec.serialize(&data)
+    }
+
     /// Enables auto-saving after every set/remove operation.
     /// Only effective with `std` feature.
     pub fn with_auto_save(mut self) -> Self {
@@ -172,665 +504,4573 @@ impl TinyKV {
         self
     }
 
-    /// Sets a namespace prefix for all keys.
-    /// Keys will be automatically prefixed when stored and accessed.
-    pub fn with_namespace(mut self, namespace: &str) -> Self {
-        self.namespace = if namespace.is_empty() {
-            String::new()
-        } else if namespace.ends_with(':') {
-            namespace.to_string()
-        } else {
-            format!("{}:", namespace)
-        };
+    /// Coalesces auto-saves from single-key writes such as `set`: a write landing within `window`
+    /// of the previous auto-save for the same key only updates memory, so rapid updates to one
+    /// key persist just the latest value while writes to other keys still save. Pending writes
+    /// are flushed by the next auto-save, an explicit `save`, or drop. Lock writes from
+    /// `acquire_lock` are never coalesced. Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn with_coalesce_window(mut self, window: Duration) -> Self {
+        self.coalesce_window = Some(window);
         self
     }
 
-    /// Helper function to add namespace prefix to a key.
-    fn namespaced_key(&self, key: &str) -> String {
-        if self.namespace.is_empty() {
-            key.to_string()
-        } else {
-            format!("{}{}", self.namespace, key)
-        }
+    /// Flushes the written file to stable storage with `fsync` on a save at most once per
+    /// `interval`, bounding how much recent data a power loss can take. On unix the
+    /// containing directory is synced too, so the rename is durable. Each flush is reported
+    /// to `with_observer` as `Op::Fsync`. Without it saves never `fsync`.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn with_fsync_interval(mut self, interval: Duration) -> Self {
+        self.fsync_interval = Some(interval);
+        self
     }
 
-    /// Helper function to remove namespace prefix from a key.
-    fn strip_namespace(&self, key: &str) -> String {
-        if self.namespace.is_empty() {
-            key.to_string()
-        } else if key.starts_with(&self.namespace) {
-            key[self.namespace.len()..].to_string()
-        } else {
-            key.to_string()
-        }
+    /// Gzip-compresses the store file on save. Compressed files are detected by their
+    /// magic bytes on load, so plain and compressed stores open the same way. Ignored for
+    /// append-log stores. Only available with `compression` feature.
+    #[cfg(feature = "compression")]
+    pub fn with_compression(mut self, enabled: bool) -> Self {
+        self.compress_file = enabled;
+        *self
+            .last_saved_hash
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+        self
     }
 
-    #[cfg(feature = "wasm")]
-    fn web_load(&mut self) -> Result<(), TinyKVError> {
-        self.load_from_localstorage()
+    /// Encrypts the store file (and checkpoints) with ChaCha20-Poly1305 under `key`, using
+    /// a fresh random nonce per save. An encrypted file can't be read by `open`; load it with
+    /// `TinyKV::new().with_encryption_key(key).load_from(path)`. A wrong key fails with
+    /// `TinyKVError::Decryption`. Append-log stores rewrite the whole file on each write.
+    /// Only available with `encryption` feature.
+    #[cfg(feature = "encryption")]
+    pub fn with_encryption_key(mut self, key: [u8; 32]) -> Self {
+        self.encryption_key = Some(key);
+        *self
+            .last_saved_hash
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+        self
     }
 
-    #[cfg(feature = "wasm")]
-    fn web_save(&self) -> Result<(), TinyKVError> {
-        self.save_to_localstorage()
+    /// Sets how long file writes and `reload` wait for another process to release the
+    /// store's `.lock` file before failing with `TinyKVError::Locked` (5 seconds by default).
+    /// Only available with `file-lock` feature.
+    #[cfg(feature = "file-lock")]
+    pub fn with_lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = timeout;
+        self
     }
 
-    #[cfg(feature = "wasm")]
-    fn load_from_localstorage(&mut self) -> Result<(), TinyKVError> {
-        let data_key = format!("{}:data", self.web_prefix);
+    /// Helper function to take the store's file lock for one save or reload. Returns
+    /// `None` when `open_locked` already holds it; the lock is released on drop.
+    #[cfg(feature = "file-lock")]
+    fn lock_for_io(&self) -> Result<Option<fs::File>, TinyKVError> {
+        if self.file_lock.is_some() || !self.is_file_backed() {
+            return Ok(None);
+        }
+        Self::lock_file(&self.path.with_extension("lock"), self.lock_timeout).map(Some)
+    }
 
-        if let Some(json_data) = wasm::ls_get_item(&data_key) {
-            let data = Self::deserialize_data(&json_data)?;
-            self.data = data;
+    /// Helper function to lock `path` exclusively, polling until `timeout` elapses.
+    #[cfg(feature = "file-lock")]
+    fn lock_file(path: &Path, timeout: Duration) -> Result<fs::File, TinyKVError> {
+        use fs2::FileExt;
+
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+        let started = Instant::now();
+        loop {
+            match file.try_lock_exclusive() {
+                Ok(()) => return Ok(file),
+                Err(e) if e.kind() != fs2::lock_contended_error().kind() => return Err(e.into()),
+                Err(_) if started.elapsed() >= timeout => return Err(TinyKVError::Locked),
+                Err(_) => std::thread::sleep(Duration::from_millis(10)),
+            }
         }
+    }
 
+    /// Helper function to `fsync` the directory holding the store file, so the rename that
+    /// replaced it survives a power loss too. A no-op outside unix.
+    #[cfg(feature = "std")]
+    fn sync_parent_dir(&self) -> io::Result<()> {
+        #[cfg(unix)]
+        {
+            let dir = match self.path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent,
+                _ => Path::new("."),
+            };
+            fs::File::open(dir)?.sync_all()?;
+        }
         Ok(())
     }
 
-    #[cfg(feature = "wasm")]
-    fn save_to_localstorage(&self) -> Result<(), TinyKVError> {
-        let data_key = format!("{}:data", self.web_prefix);
-        let json_data = self.serialize_data()?;
-
-        wasm::ls_set_item(&data_key, &json_data);
-        Ok(())
+    /// Helper function to decide whether this save should `fsync`, recording the time if so.
+    #[cfg(feature = "std")]
+    fn fsync_due(&self) -> bool {
+        let Some(interval) = self.fsync_interval else {
+            return false;
+        };
+        let mut last_fsync = self
+            .last_fsync
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let now = Instant::now();
+        if last_fsync.is_some_and(|last| now.duration_since(last) < interval) {
+            return false;
+        }
+        *last_fsync = Some(now);
+        true
     }
 
-    // Helper method for serialization
-    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
-    fn serialize_data(&self) -> Result<String, TinyKVError> {
-        serde_json::to_string_pretty(&self.data).map_err(Into::into)
+    /// Helper function to auto-save after a write to `key`, unless the previous auto-save
+    /// for the same key is within the coalesce window. Keys whose window has passed are
+    /// forgotten on every save, so the map only holds recently written keys.
+    #[cfg(feature = "std")]
+    fn coalesced_auto_save(&mut self, key: &str) -> Result<(), TinyKVError> {
+        if !self.auto_save {
+            return Ok(());
+        }
+        let Some(window) = self.coalesce_window else {
+            return self.save();
+        };
+        let now = Instant::now();
+        let namespaced_key = self.namespaced_key(key);
+        if self
+            .last_key_saves
+            .get(&namespaced_key)
+            .is_some_and(|last| now.duration_since(*last) < window)
+        {
+            return Ok(());
+        }
+
+        self.save()?;
+        self.last_key_saves
+            .retain(|_, last| now.duration_since(*last) < window);
+        self.last_key_saves.insert(namespaced_key, now);
+        Ok(())
     }
 
-    #[cfg(feature = "nanoserde")]
-    fn serialize_data(&self) -> Result<String, TinyKVError> {
-        Ok(self.data.serialize_json())
+    /// Helper function to persist a single-key write: appends the record in
+    /// append-log mode, otherwise falls back to the (coalesced) auto-save.
+    #[cfg(feature = "std")]
+    fn persist_write(&mut self, key: &str) -> Result<(), TinyKVError> {
+        if self.record_write(key)? {
+            self.coalesced_auto_save(key)?;
+        }
+        Ok(())
     }
 
-    #[cfg(all(not(feature = "nanoserde"), not(feature = "std"), feature = "wasm"))]
-    fn deserialize_data(_contents: &str) -> Result<HashMap<String, Entry>, TinyKVError> {
-        // Simple deserialization for WASM no_std (basic implementation)
-        // In practice, you'd want a proper JSON parser here
-        Err(TinyKVError::NoStdUnsupported(
-            "JSON deserialization not implemented for WASM no_std without nanoserde".to_string(),
-        ))
+    /// Helper function for the per-key half of `persist_write`, so bulk writers can
+    /// run it for every key and save once. Returns whether an auto-save is still
+    /// needed, which is the case unless the record went to the append log.
+    #[cfg(feature = "std")]
+    fn record_write(&mut self, key: &str) -> Result<bool, TinyKVError> {
+        log_op!(trace, "tinykv: set {}", self.log_key(key));
+        self.notify_change(key, ChangeKind::Set);
+        self.maybe_auto_purge()?;
+        self.evict_over_capacity(&self.namespaced_key(key))?;
+        self.check_soft_limit();
+        let append = self.appends_records();
+        if append {
+            self.append_record(&self.namespaced_key(key))?;
+        }
+        Ok(!append)
     }
 
-    #[cfg(all(not(feature = "nanoserde"), not(feature = "std"), feature = "wasm"))]
-    fn serialize_data(&self) -> Result<String, TinyKVError> {
-        // Simple JSON serialization for WASM no_std
-        let mut result = String::from("{");
-        let mut first = true;
+    /// Helper function to drop expired entries once they make up the configured
+    /// share of the store. Expired entries are a subset of TTL entries, so the full
+    /// scan is skipped while the running TTL count alone is below the ratio. Each scan
+    /// also records how many entries had expired and the earliest expiry still ahead;
+    /// until that expiry passes, no more entries can have expired and the scan is skipped.
+    #[cfg(feature = "std")]
+    fn maybe_auto_purge(&mut self) -> Result<(), TinyKVError> {
+        let Some(ratio) = self.auto_purge_ratio else {
+            return Ok(());
+        };
+        let threshold = ratio * self.data.len() as f64;
+        if (self.ttl_entries as f64) < threshold {
+            return Ok(());
+        }
+        let now = Self::current_timestamp()?;
+        if now <= self.next_ttl_expiry && (self.known_expired as f64) < threshold {
+            return Ok(());
+        }
 
-        for (key, entry) in &self.data {
-            if !first {
-                result.push(',');
+        let mut expired = 0;
+        let mut next_expiry = u64::MAX;
+        for expiry in self.data.values().filter_map(|entry| entry.expires_at) {
+            if now > expiry {
+                expired += 1;
+            } else {
+                next_expiry = next_expiry.min(expiry);
             }
-            first = false;
-
-            result.push_str(&format!(
-                r#""{}":{{"value":"{}","expires_at":{}}}"#,
-                key,
-                entry.value,
-                match entry.expires_at {
-                    Some(exp) => exp.to_string(),
-                    None => "null".to_string(),
+        }
+        if expired > 0 && expired as f64 >= threshold {
+            let mut purged = Vec::new();
+            self.data.retain(|key, entry| {
+                let live = entry.expires_at.is_none_or(|expiry| now <= expiry);
+                if !live {
+                    purged.push(key.clone());
                 }
-            ));
-        }
-
-        result.push('}');
-        Ok(result)
-    }
-
-    #[cfg(all(not(feature = "nanoserde"), not(feature = "std"), feature = "wasm"))]
-    pub fn set(&mut self, key: &str, value: &str) -> Result<(), TinyKVError> {
-        self.data.insert(
-            key.to_string(),
-            Entry {
-                value: value.to_string(),
-                expires_at: None,
-            },
-        );
-
-        if self.auto_save {
-            self.web_save()?;
+                live
+            });
+            self.invalidate_read_cache();
+            self.refresh_ttl_flag();
+            expired = 0;
+            if self.appends_records() {
+                self.append_tombstones(&purged)?;
+            }
         }
+        self.next_ttl_expiry = next_expiry;
+        self.known_expired = expired;
         Ok(())
     }
 
-    #[cfg(all(not(feature = "nanoserde"), not(feature = "std"), feature = "wasm"))]
-    pub fn set_with_ttl(
-        &mut self,
-        key: &str,
-        value: &str,
-        ttl_secs: u64,
-    ) -> Result<(), TinyKVError> {
-        let expires_at = Some(Self::current_timestamp()? + ttl_secs);
-
-        self.data.insert(
-            key.to_string(),
-            Entry {
-                value: value.to_string(),
-                expires_at,
-            },
-        );
-
-        if self.auto_save {
-            self.web_save()?;
+    /// Helper function to fire the soft-limit callback once each time the entry
+    /// count rises above the limit, re-arming after it drops back to or below it.
+    #[cfg(feature = "std")]
+    fn check_soft_limit(&mut self) {
+        let Some((limit, callback)) = &self.soft_limit else {
+            return;
+        };
+        let count = self.data.len();
+        if count <= *limit {
+            self.soft_limit_exceeded = false;
+        } else if !self.soft_limit_exceeded {
+            self.soft_limit_exceeded = true;
+            callback(count);
         }
-        Ok(())
     }
 
-    #[cfg(all(not(feature = "nanoserde"), not(feature = "std"), feature = "wasm"))]
-    pub fn get(&self, key: &str) -> Option<String> {
-        let now = Self::current_timestamp().unwrap_or(0);
+    /// Helper function to evict entries chosen by the eviction policy until the
+    /// store fits its capacity. The entry that was just written is never evicted.
+    #[cfg(feature = "std")]
+    fn evict_over_capacity(&mut self, written_key: &str) -> Result<(), TinyKVError> {
+        let Some(capacity) = self.capacity else {
+            return Ok(());
+        };
+        let mut evicted = Vec::new();
+        while self.data.len() - usize::from(self.data.contains_key(VERSION_KEY)) > capacity {
+            let policy = self.eviction_policy;
+            let victim = self
+                .data
+                .iter()
+                .filter(|(key, _)| key.as_str() != written_key && !Self::is_reserved_key(key))
+                .min_by_key(|(_, entry)| {
+                    // The in-memory sequence numbers order this session's writes and reads
+                    // exactly; entries loaded from disk have none and fall back to the
+                    // second-resolution timestamps.
+                    let last_access = entry.accessed_at.or(entry.created_at).unwrap_or(0);
+                    match policy {
+                        EvictionPolicy::Lru => (0, entry.used_seq, last_access),
+                        EvictionPolicy::Lfu => (entry.hits, entry.used_seq, last_access),
+                        EvictionPolicy::Fifo => {
+                            (0, entry.inserted_seq, entry.created_at.unwrap_or(0))
+                        }
+                    }
+                })
+                .map(|(key, _)| key.clone());
 
-        if let Some(entry) = self.data.get(key) {
-            if let Some(expiry) = entry.expires_at {
-                if now > expiry {
-                    return None;
+            match victim {
+                Some(key) => {
+                    self.remove_entry(&key);
+                    let stripped = self.strip_namespace(&key);
+                    log_op!(debug, "tinykv: evicted {}", self.log_key(&stripped));
+                    if let Some(callback) = &mut self.evict_callback {
+                        callback(stripped);
+                    }
+                    evicted.push(key);
                 }
+                None => break,
             }
-            return Some(entry.value.clone());
         }
-        None
+        if self.appends_records() {
+            self.append_tombstones(&evicted)?;
+        }
+        Ok(())
     }
 
-    #[cfg(all(
-        not(feature = "nanoserde"),
-        not(feature = "std"),
-        not(feature = "wasm")
-    ))]
-    fn serialize_data(&self) -> Result<String, TinyKVError> {
-        // Simple JSON serialization for no_std
-        let mut result = String::from("{");
-        let mut first = true;
-
-        for (key, entry) in &self.data {
-            if !first {
-                result.push(',');
-            }
-            first = false;
+    /// Helper function to count entries that carry a TTL.
+    #[cfg(feature = "std")]
+    fn count_ttl_entries(&self) -> usize {
+        self.data
+            .values()
+            .filter(|entry| entry.expires_at.is_some())
+            .count()
+    }
 
-            result.push_str(&format!(
-                r#""{}":{{"value":"{}","expires_at":{}}}"#,
-                key,
-                entry.value,
-                match entry.expires_at {
-                    Some(exp) => exp.to_string(),
-                    None => "null".to_string(),
-                }
-            ));
+    /// Helper function to drop every value cached by `get_cached` after the entries
+    /// are replaced or removed in bulk.
+    fn invalidate_read_cache(&mut self) {
+        #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+        if let Some(cache) = &mut self.read_cache {
+            cache.clear();
         }
+    }
 
-        result.push('}');
-        Ok(result)
+    /// Helper function to tell whether writes go to the log as records instead of
+    /// through a full save. Encrypted stores always save in full, and nothing is
+    /// appended while a transaction runs, since it saves once on commit.
+    #[cfg(feature = "std")]
+    fn appends_records(&self) -> bool {
+        #[cfg(feature = "encryption")]
+        let encrypted = self.encryption_key.is_some();
+        #[cfg(not(feature = "encryption"))]
+        let encrypted = false;
+        self.append_log && !self.suppress_writes && !encrypted
     }
 
-    // Helper method for deserialization
-    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
-    fn deserialize_data(contents: &str) -> Result<HashMap<String, Entry>, TinyKVError> {
-        if contents.trim().is_empty() {
-            return Ok(HashMap::new());
+    /// Helper function to append one entry as a record line to the log file.
+    #[cfg(feature = "std")]
+    fn append_record(&self, namespaced_key: &str) -> Result<(), TinyKVError> {
+        let Some(entry) = self.data.get(namespaced_key) else {
+            return Ok(());
+        };
+        let mut record = Self::serialize_record(namespaced_key, entry)?;
+        record.push('\n');
+        self.append_lines(&record)
+    }
+
+    /// Helper function to append a tombstone line per removed key to the log file, so
+    /// replay drops the keys again.
+    #[cfg(feature = "std")]
+    fn append_tombstones(&self, namespaced_keys: &[String]) -> Result<(), TinyKVError> {
+        if namespaced_keys.is_empty() {
+            return Ok(());
         }
-        serde_json::from_str(contents)
-            .map_err(|e| TinyKVError::Io(io::Error::new(ErrorKind::InvalidData, e)))
+        let mut lines = String::new();
+        for key in namespaced_keys {
+            lines.push_str(&Self::serialize_tombstone(key)?);
+            lines.push('\n');
+        }
+        self.append_lines(&lines)
     }
 
-    #[cfg(all(feature = "nanoserde", any(feature = "std", feature = "wasm")))]
-    fn deserialize_data(contents: &str) -> Result<HashMap<String, Entry>, TinyKVError> {
-        if contents.trim().is_empty() {
-            return Ok(HashMap::new());
+    /// Helper function to persist removed keys: appends tombstones in append-log mode,
+    /// otherwise falls back to the auto-save.
+    #[cfg(feature = "std")]
+    fn persist_removals(&self, namespaced_keys: &[String]) -> Result<(), TinyKVError> {
+        if self.appends_records() {
+            self.append_tombstones(namespaced_keys)
+        } else if self.auto_save && !namespaced_keys.is_empty() {
+            self.save()
+        } else {
+            Ok(())
         }
-        HashMap::<String, Entry>::deserialize_json(contents)
-            .map_err(|e| TinyKVError::Serialization(e.to_string()))
     }
 
-    #[cfg(all(feature = "nanoserde", not(feature = "std"), not(feature = "wasm")))]
-    fn deserialize_data(contents: &str) -> Result<BTreeMap<String, Entry>, TinyKVError> {
-        if contents.trim().is_empty() {
-            return Ok(BTreeMap::new());
+    /// Helper function to persist renamed keys: appends tombstones for the old keys and
+    /// records for the new ones in append-log mode, otherwise falls back to the auto-save.
+    #[cfg(feature = "std")]
+    fn persist_moves(&self, from: &[String], to: &[String]) -> Result<(), TinyKVError> {
+        if !self.appends_records() {
+            return self.persist_removals(from);
         }
-        BTreeMap::<String, Entry>::deserialize_json(contents)
-            .map_err(|e| TinyKVError::Serialization(e.to_string()))
+        self.append_tombstones(from)?;
+        for key in to {
+            self.append_record(key)?;
+        }
+        Ok(())
     }
 
-    #[cfg(all(
-        not(feature = "nanoserde"),
-        not(feature = "std"),
-        not(feature = "wasm")
-    ))]
-    fn deserialize_data(_contents: &str) -> Result<BTreeMap<String, Entry>, TinyKVError> {
-        // Simple deserialization for no_std (basic implementation)
-        // In practice, you'd want a proper JSON parser here
-        Err(TinyKVError::NoStdUnsupported(
-            "JSON deserialization not implemented for no_std without nanoserde".to_string(),
-        ))
+    /// Helper function to append raw lines to the log file.
+    #[cfg(feature = "std")]
+    fn append_lines(&self, lines: &str) -> Result<(), TinyKVError> {
+        #[cfg(feature = "file-lock")]
+        let _lock = self.lock_for_io()?;
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(lines.as_bytes())?;
+        Ok(())
     }
 
+    /// Transforms the serialized store text right before `save` writes it,
+    /// e.g. to strip secrets or add a header. Must be the inverse of the
+    /// `with_post_load` hook, or the file will not load back correctly.
+    /// Not applied in append-log mode. Only available with `std` feature.
     #[cfg(feature = "std")]
-    fn current_timestamp() -> Result<u64, TinyKVError> {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map_err(|_| TinyKVError::TimeError)
-            .map(|d| d.as_secs())
+    pub fn with_pre_save(mut self, hook: Box<dyn Fn(String) -> String + Send + Sync>) -> Self {
+        self.pre_save = Some(hook);
+        self
     }
 
-    #[cfg(all(feature = "wasm", not(feature = "std")))]
-    fn current_timestamp() -> Result<u64, TinyKVError> {
-        Ok(wasm::current_timestamp())
+    /// Transforms the file contents right after they are read by `load_from`
+    /// or `reload`, before parsing. Must be the inverse of the `with_pre_save`
+    /// hook, or data will be corrupted. Not applied in append-log mode.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn with_post_load(mut self, hook: Box<dyn Fn(String) -> String + Send + Sync>) -> Self {
+        self.post_load = Some(hook);
+        self
     }
 
-    #[cfg(all(not(feature = "std"), not(feature = "wasm")))]
-    #[allow(dead_code)]
-    fn current_timestamp() -> Result<u64, TinyKVError> {
-        Err(TinyKVError::NoStdUnsupported(
-            "System time not available in no_std".to_string(),
-        ))
+    /// Automatically purges expired entries on a write once they make up at least
+    /// `ratio` (0.0 to 1.0) of the store. A running count of TTL entries keeps the
+    /// check cheap while few entries carry a TTL, and the store is only rescanned once
+    /// another entry may have expired.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn with_auto_purge_ratio(mut self, ratio: f64) -> Self {
+        self.auto_purge_ratio = Some(ratio);
+        self
     }
 
-    /// Inserts a key with a value (without expiration).
-    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
-    pub fn set<T: Serialize>(&mut self, key: &str, value: T) -> Result<(), TinyKVError> {
-        let val = serde_json::to_value(value)?;
-        let namespaced_key = self.namespaced_key(key);
-        self.data.insert(
-            namespaced_key,
-            Entry {
-                value: val,
-                expires_at: None,
-            },
-        );
+    /// Drops expired entries as soon as the store is loaded: immediately for the data
+    /// already read by `open`, and after every later `reload` or `load_from`. Purging is
+    /// best-effort; if the clock can't be read the entries are left for lazy cleanup.
+    /// Append-log stores also append tombstones for the purged keys.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn with_purge_on_load(mut self, enabled: bool) -> Self {
+        self.purge_on_load = enabled;
+        // Expired entries are skipped on every load anyway, so a failed tombstone
+        // append loses nothing.
+        let _ = self.purge_loaded();
+        self.refresh_ttl_flag();
+        self
+    }
 
-        if self.auto_save {
-            #[cfg(feature = "std")]
-            self.save()?;
-            #[cfg(feature = "wasm")]
-            self.web_save()?;
+    /// Helper function to drop expired entries right after a load when
+    /// `with_purge_on_load` is enabled.
+    #[cfg(feature = "std")]
+    fn purge_loaded(&mut self) -> Result<(), TinyKVError> {
+        if !self.purge_on_load {
+            return Ok(());
+        }
+        let Ok(now) = Self::current_timestamp() else {
+            return Ok(());
+        };
+        let expired: Vec<String> = self
+            .data
+            .iter()
+            .filter(|(_, entry)| entry.expires_at.is_some_and(|expiry| now > expiry))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &expired {
+            self.remove_entry(key);
+        }
+        if self.appends_records() && !self.read_only {
+            self.append_tombstones(&expired)?;
         }
         Ok(())
     }
 
-    #[cfg(feature = "nanoserde")]
-    pub fn set<T: SerJson>(&mut self, key: &str, value: T) -> Result<(), TinyKVError> {
-        let json_str = value.serialize_json();
-        let namespaced_key = self.namespaced_key(key);
-        self.data.insert(
-            namespaced_key,
-            Entry {
-                value: json_str,
-                expires_at: None,
-            },
-        );
+    /// Upgrades data written under an older schema. The version stamped in the store
+    /// (0 if none) is read from a reserved key; if it is below `current_version`,
+    /// `f(stored_version, &mut entries)` transforms the entries before first use, then
+    /// `current_version` is stamped and a file-backed store is saved. The reserved key
+    /// is hidden from `keys()` and `len()`. Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn with_migration<F>(mut self, current_version: u32, f: F) -> Result<Self, TinyKVError>
+    where
+        F: FnOnce(u32, &mut HashMap<String, Entry>) -> Result<(), TinyKVError>,
+    {
+        let stored_version = self.stored_version()?;
+        if stored_version >= current_version {
+            return Ok(self);
+        }
 
-        if self.auto_save {
-            #[cfg(feature = "std")]
+        self.data.remove(VERSION_KEY);
+        f(stored_version, &mut self.data)?;
+        self.invalidate_read_cache();
+        let now = Self::current_timestamp()?;
+        self.data
+            .insert(VERSION_KEY.to_string(), Self::version_entry(current_version, now));
+        self.refresh_ttl_flag();
+
+        if self.is_file_backed() {
             self.save()?;
-            #[cfg(feature = "wasm")]
-            self.web_save()?;
         }
-        Ok(())
+        Ok(self)
     }
 
-    #[cfg(all(
-        not(feature = "nanoserde"),
-        not(feature = "std"),
-        not(feature = "wasm")
-    ))]
-    pub fn set(&mut self, key: &str, value: &str) -> Result<(), TinyKVError> {
-        let namespaced_key = self.namespaced_key(key);
-        self.data.insert(
-            namespaced_key,
-            Entry {
-                value: value.to_string(),
-                expires_at: None,
-            },
-        );
-        Ok(())
+    /// Helper function to read the schema version stamped by `with_migration`.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    fn stored_version(&self) -> Result<u32, TinyKVError> {
+        let Some(entry) = self.data.get(VERSION_KEY) else {
+            return Ok(0);
+        };
+        entry
+            .value
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| TinyKVError::Serialization("invalid schema version".to_string()))
     }
 
-    /// Inserts a key with value and expiration (TTL in seconds).
+    #[cfg(all(feature = "nanoserde", feature = "std"))]
+    fn stored_version(&self) -> Result<u32, TinyKVError> {
+        let Some(entry) = self.data.get(VERSION_KEY) else {
+            return Ok(0);
+        };
+        u32::deserialize_json(&entry.value).map_err(|e| TinyKVError::Serialization(e.to_string()))
+    }
+
+    /// Helper function to build the reserved entry stamping `version`.
     #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
-    pub fn set_with_ttl<T: Serialize>(
-        &mut self,
-        key: &str,
-        value: T,
-        ttl_secs: u64,
-    ) -> Result<(), TinyKVError> {
-        let val = serde_json::to_value(value)?;
-        let expires_at = Some(Self::current_timestamp()? + ttl_secs);
-        let namespaced_key = self.namespaced_key(key);
+    fn version_entry(version: u32, now: u64) -> Entry {
+        Entry::new(serde_json::Value::from(version), None, Some(now))
+    }
 
-        self.data.insert(
-            namespaced_key,
-            Entry {
-                value: val,
-                expires_at,
-            },
-        );
+    #[cfg(all(feature = "nanoserde", feature = "std"))]
+    fn version_entry(version: u32, now: u64) -> Entry {
+        Entry::new(version.serialize_json(), None, Some(now))
+    }
 
-        if self.auto_save {
-            #[cfg(feature = "std")]
-            self.save()?;
-            #[cfg(feature = "wasm")]
-            self.web_save()?;
+    /// Replaces keys with `<redacted>` in log records emitted by the `logging` feature.
+    #[cfg(feature = "logging")]
+    pub fn with_redacted_log_keys(mut self) -> Self {
+        self.redact_log_keys = true;
+        self
+    }
+
+    /// Helper function to return the key as it should appear in log records.
+    #[cfg(feature = "logging")]
+    fn log_key<'a>(&self, key: &'a str) -> &'a str {
+        if self.redact_log_keys {
+            "<redacted>"
+        } else {
+            key
         }
-        Ok(())
     }
 
-    #[cfg(feature = "nanoserde")]
-    pub fn set_with_ttl<T: SerJson>(
-        &mut self,
-        key: &str,
-        value: T,
-        ttl_secs: u64,
-    ) -> Result<(), TinyKVError> {
-        let json_str = value.serialize_json();
-        let expires_at = Some(Self::current_timestamp()? + ttl_secs);
-        let namespaced_key = self.namespaced_key(key);
+    /// Calls `callback` with the current entry count when a write pushes the store
+    /// above `limit` entries. Fires once per crossing, not on every write; writes are
+    /// never rejected. Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn with_soft_limit(
+        mut self,
+        limit: usize,
+        callback: Box<dyn Fn(usize) + Send + Sync>,
+    ) -> Self {
+        self.soft_limit = Some((limit, callback));
+        self
+    }
 
-        self.data.insert(
-            namespaced_key,
-            Entry {
-                value: json_str,
-                expires_at,
-            },
-        );
+    /// Enables a per-key cache of deserialized values for `get_cached`, so repeated reads
+    /// of an unchanged value skip re-parsing. Only available with `std` feature.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn with_read_cache(mut self) -> Self {
+        self.read_cache = Some(HashMap::new());
+        self
+    }
 
-        if self.auto_save {
-            #[cfg(feature = "std")]
-            self.save()?;
-            #[cfg(feature = "wasm")]
-            self.web_save()?;
+    /// Stores values whose serialized JSON is longer than `bytes` gzip-compressed (marked
+    /// per entry); smaller values stay plain. Reads decompress transparently.
+    /// Only available with `compression` feature.
+    #[cfg(all(not(feature = "nanoserde"), feature = "compression"))]
+    pub fn with_compress_above(mut self, bytes: usize) -> Self {
+        self.compress_above = Some(bytes);
+        self
+    }
+
+    /// Calls `observer` with the elapsed time after each save, load, (de)serialization and
+    /// `fsync` of the store file, and successful `get`/`set`, for profiling.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn with_observer(mut self, observer: Box<dyn Fn(Op, Duration) + Send + Sync>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// Helper function to report how long an operation took to the observer, if any.
+    #[cfg(feature = "std")]
+    fn observe(&self, op: Op, started: Instant) {
+        if let Some(observer) = &self.observer {
+            observer(op, started.elapsed());
         }
-        Ok(())
     }
 
-    #[cfg(all(
-        not(feature = "nanoserde"),
-        not(feature = "std"),
-        not(feature = "wasm")
-    ))]
-    pub fn set_with_ttl(
-        &mut self,
-        key: &str,
-        value: &str,
-        _ttl_secs: u64,
-    ) -> Result<(), TinyKVError> {
-        // TTL not supported in no_std without time
-        self.set(key, value)
+    /// Registers `f` to be called after every mutation with the namespace-stripped key and
+    /// what happened to it: `Set` for each key written (`set`, `update`, `set_many`,
+    /// `for_each_mut`, ...), `Removed` for `remove` and `remove_returning`, `Expired` when a
+    /// read drops an expired key, and `Cleared` (with an empty key) for `clear`. Prefix and
+    /// purge operations don't notify. Several callbacks may be registered; they run in
+    /// registration order. Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn on_change<F>(&mut self, f: F)
+    where
+        F: FnMut(&str, ChangeKind) + Send + Sync + 'static,
+    {
+        self.change_callbacks.push(Box::new(f));
     }
 
-    /// Retrieves the value for a given key if it exists and hasn't expired.
-    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
-    pub fn get<T: for<'de> Deserialize<'de>>(
-        &mut self,
-        key: &str,
-    ) -> Result<Option<T>, TinyKVError> {
-        let now = Self::current_timestamp()?;
-        let namespaced_key = self.namespaced_key(key);
-
-        if let Some(entry) = self.data.get(&namespaced_key) {
-            if let Some(expiry) = entry.expires_at {
-                if now > expiry {
-                    self.data.remove(&namespaced_key);
-                    if self.auto_save {
-                        #[cfg(feature = "std")]
-                        self.save()?;
-                        #[cfg(feature = "wasm")]
-                        self.web_save()?;
-                    }
-                    return Ok(None);
-                }
-            }
-
-            let value = serde_json::from_value(entry.value.clone())?;
-            return Ok(Some(value));
+    /// Helper function to run the `on_change` callbacks for a (namespace-stripped) key.
+    #[cfg(feature = "std")]
+    fn notify_change(&mut self, key: &str, kind: ChangeKind) {
+        for callback in &mut self.change_callbacks {
+            callback(key, kind);
         }
+    }
 
-        Ok(None)
+    /// Caps the store at `capacity` entries; writes that overflow it evict entries
+    /// chosen by the eviction policy (LRU unless set with `with_eviction`).
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
     }
 
-    #[cfg(feature = "nanoserde")]
-    pub fn get<T: DeJson>(&mut self, key: &str) -> Result<Option<T>, TinyKVError> {
-        #[cfg(any(feature = "std", feature = "wasm"))]
-        let now = Self::current_timestamp()?;
-        let namespaced_key = self.namespaced_key(key);
+    /// Returns the capacity set with `with_capacity`, or `None` if the store is unbounded.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn capacity(&self) -> Option<usize> {
+        self.capacity
+    }
 
-        if let Some(entry) = self.data.get(&namespaced_key) {
-            #[cfg(any(feature = "std", feature = "wasm"))]
-            if let Some(expiry) = entry.expires_at {
-                if now > expiry {
-                    self.data.remove(&namespaced_key);
-                    if self.auto_save {
-                        #[cfg(feature = "std")]
-                        self.save()?;
-                        #[cfg(feature = "wasm")]
-                        self.web_save()?;
-                    }
-                    return Ok(None);
-                }
-            }
+    /// Registers `f` to be called with the namespace-stripped key of every entry that
+    /// capacity eviction drops, replacing any previous callback.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn on_evict<F>(&mut self, f: F)
+    where
+        F: FnMut(String) + Send + Sync + 'static,
+    {
+        self.evict_callback = Some(Box::new(f));
+    }
 
-            let value = T::deserialize_json(&entry.value)
-                .map_err(|e| TinyKVError::Serialization(e.to_string()))?;
-            return Ok(Some(value));
-        }
+    /// Selects which entry is evicted when a write overflows `with_capacity`.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn with_eviction(mut self, policy: EvictionPolicy) -> Self {
+        self.eviction_policy = policy;
+        self
+    }
 
-        Ok(None)
+    /// Writes backups as `<dir>/<filename>.bak` instead of next to the store file.
+    /// The directory is created on save if missing. Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn with_backup_dir(mut self, dir: PathBuf) -> Self {
+        self.backup_dir = Some(dir);
+        self
     }
 
-    #[cfg(all(
-        not(feature = "nanoserde"),
-        not(feature = "std"),
-        not(feature = "wasm")
-    ))]
-    pub fn get(&self, key: &str) -> Option<String> {
-        let namespaced_key = self.namespaced_key(key);
-        self.data.get(&namespaced_key).map(|entry| entry.value.clone())
+    /// Keeps the last `count` backups as `.bak.1` (newest) through `.bak.<count>`, shifting
+    /// older ones down on each save; `0` keeps today's single `.bak`. Backups must still be
+    /// enabled with `with_backup(true)`. Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn with_backup_rotation(self, count: usize) -> Self {
+        self.with_backup_strategy(match count {
+            0 => BackupStrategy::Overwrite,
+            count => BackupStrategy::Rotating(count),
+        })
     }
 
-    /// Removes a key from the store.
-    pub fn remove(&mut self, key: &str) -> Result<bool, TinyKVError> {
-        let namespaced_key = self.namespaced_key(key);
-        let removed = self.data.remove(&namespaced_key).is_some();
+    /// Chooses how backups are named and kept (see `BackupStrategy`). Backups must still
+    /// be enabled with `with_backup(true)`. Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn with_backup_strategy(mut self, strategy: BackupStrategy) -> Self {
+        self.backup_strategy = strategy;
+        self
+    }
 
-        if removed && self.auto_save {
-            #[cfg(feature = "std")]
-            self.save()?;
-            #[cfg(feature = "wasm")]
-            self.web_save()?;
-        }
+    /// Creates missing parent directories of the store file on save.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn with_create_dirs(mut self) -> Self {
+        self.create_dirs = true;
+        self
+    }
 
-        Ok(removed)
+    /// Enables opportunistic cleanup: every `set`/`get` examines one entry, taking keys in
+    /// turn from a snapshot of the store, and evicts it if expired, amortizing purge work
+    /// over normal use. Every key is examined once per `len()` calls.
+    #[cfg(any(feature = "std", feature = "wasm"))]
+    pub fn with_opportunistic_purge(mut self) -> Self {
+        self.opportunistic_purge = true;
+        self
     }
 
-    /// Checks if the store contains a given key and it's not expired.
-    pub fn contains_key(&self, key: &str) -> bool {
-        let namespaced_key = self.namespaced_key(key);
-        if let Some(_entry) = self.data.get(&namespaced_key) {
-            #[cfg(any(feature = "std", feature = "wasm"))]
-            if let Some(expiry) = _entry.expires_at {
-                let now = Self::current_timestamp().unwrap_or(0);
-                return now <= expiry;
-            }
-            return true;
-        }
-        false
+    /// Sets a predicate that every value must satisfy before it is stored.
+    /// The check runs on the serialized `serde_json::Value`; values that fail it
+    /// are rejected with `TinyKVError::ValidationFailed`.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn with_value_validator(mut self, f: fn(&serde_json::Value) -> bool) -> Self {
+        self.value_validator = Some(f);
+        self
     }
 
-    /// Returns a list of all unexpired keys in the store.
-    /// If namespace is set, returns keys with namespace prefix stripped.
-    pub fn keys(&self) -> Vec<String> {
-        #[cfg(any(feature = "std", feature = "wasm"))]
-        let now = Self::current_timestamp().unwrap_or(0);
+    /// Sets the format used to write the store file and to read it back on `reload`,
+    /// `load_from` and `reset_to_data`. `open` and `from_data` always read JSON, so pair
+    /// a non-JSON codec with `TinyKV::new().with_codec(codec).load_from(path)`.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn with_codec(mut self, codec: impl Codec + 'static) -> Self {
+        self.codec = Box::new(codec);
+        self.custom_codec = true;
+        self
+    }
 
-        self.data
-            .iter()
-            .filter(|(key, _entry)| {
-                // If namespace is set, only include keys from this namespace
-                if !self.namespace.is_empty() && !key.starts_with(&self.namespace) {
-                    return false;
-                }
-                
-                // Check expiration
-                #[cfg(any(feature = "std", feature = "wasm"))]
-                match _entry.expires_at {
-                    Some(expiry) => now <= expiry,
-                    None => true,
-                }
-                #[cfg(all(not(feature = "std"), not(feature = "wasm")))]
-                true
-            })
-            .map(|(k, _)| self.strip_namespace(k))
-            .collect()
+    /// Makes `load_from` and `reload` reject a file in which a key appears more than once,
+    /// like `open_strict`, while still applying `with_post_load`. The check only applies to
+    /// the built-in JSON codecs; a codec set with `with_codec` parses the file as usual.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn with_strict_load(mut self) -> Self {
+        self.strict_load = true;
+        self
     }
 
-    /// Returns a list of all unexpired keys that start with the given prefix.
-    pub fn list_keys(&self, prefix: &str) -> Vec<String> {
-        #[cfg(any(feature = "std", feature = "wasm"))]
-        let now = Self::current_timestamp().unwrap_or(0);
+    /// Chooses between indented (`true`, the default) and single-line JSON for the store
+    /// file. Only switches between the built-in `PrettyJsonCodec` and `CompactJsonCodec`;
+    /// a codec set with `with_codec` is kept.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn with_pretty(mut self, pretty: bool) -> Self {
+        if !self.custom_codec {
+            self.codec = if pretty {
+                Box::new(PrettyJsonCodec)
+            } else {
+                Box::new(CompactJsonCodec)
+            };
+        }
+        self
+    }
 
-        self.data
-            .iter()
-            .filter(|(key, _entry)| {
-                // Check prefix
-                if !key.starts_with(prefix) {
-                    return false;
-                }
-                
-                // Check expiration
-                #[cfg(any(feature = "std", feature = "wasm"))]
-                match _entry.expires_at {
-                    Some(expiry) => now <= expiry,
-                    None => true,
-                }
-                #[cfg(all(not(feature = "std"), not(feature = "wasm")))]
-                true
-            })
-            .map(|(k, _)| k.clone())
-            .collect()
+    /// Makes reads substitute `f(key)` for a stored value that fails to parse, instead of
+    /// failing, so one corrupt entry doesn't break `get` or bulk reads such as
+    /// `for_each_value`. The stored value itself is left untouched.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn with_parse_error_recovery(
+        mut self,
+        f: Box<dyn Fn(&str) -> serde_json::Value + Send + Sync>,
+    ) -> Self {
+        self.parse_error_recovery = Some(f);
+        self
     }
 
-    /// Returns number of unexpired entries.
-    pub fn len(&self) -> usize {
-        #[cfg(any(feature = "std", feature = "wasm"))]
-        let now = Self::current_timestamp().unwrap_or(0);
+    /// Helper function to deserialize an entry's value, falling back to the
+    /// parse-error recovery value (if configured) when it doesn't parse as `T`.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    fn parse_entry<T: for<'de> Deserialize<'de>>(
+        &self,
+        key: &str,
+        entry: &Entry,
+    ) -> Result<T, TinyKVError> {
+        let parsed = entry
+            .decoded_value()
+            .and_then(|value| T::deserialize(value.as_ref()).map_err(Into::into));
 
-        self.data
-            .iter()
-            .filter(|(_, _entry)| {
-                #[cfg(any(feature = "std", feature = "wasm"))]
-                match _entry.expires_at {
-                    Some(expiry) => now <= expiry,
-                    None => true,
-                }
-                #[cfg(all(not(feature = "std"), not(feature = "wasm")))]
-                true
-            })
-            .count()
+        match (parsed, &self.parse_error_recovery) {
+            (Err(_), Some(recover)) => {
+                log_op!(warn, "tinykv: substituting default for unparsable {}", self.log_key(key));
+                Ok(serde_json::from_value(recover(key))?)
+            }
+            (parsed, _) => parsed,
+        }
     }
 
-    /// Returns true if the store is empty.
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
+    /// Helper function to record whether any entry carries a TTL after the data is
+    /// replaced wholesale, letting `len` skip the expiry scan when none do. With `std`
+    /// this also recounts the TTL entries used by `with_auto_purge_ratio`.
+    #[cfg(any(feature = "std", feature = "wasm"))]
+    fn refresh_ttl_flag(&mut self) {
+        #[cfg(feature = "std")]
+        {
+            self.ttl_entries = self.count_ttl_entries();
+            self.may_have_ttl = self.ttl_entries > 0;
+            self.next_ttl_expiry = 0;
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            self.may_have_ttl = self.data.values().any(|entry| entry.expires_at.is_some());
+        }
     }
 
-    /// Save contents to disk. Creates a `.bak` file if backup is enabled.
-    /// Only available with `std` feature.
-    #[cfg(feature = "std")]
-    pub fn save(&self) -> Result<(), TinyKVError> {
-        if self.backup_enabled && self.path.exists() {
-            let backup_path = self.path.with_extension("bak");
-            fs::copy(&self.path, &backup_path)?;
+    /// Helper function to run the configured value validator, if any.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    fn validate_value(&self, key: &str, value: &serde_json::Value) -> Result<(), TinyKVError> {
+        match self.value_validator {
+            Some(validator) if !validator(value) => {
+                Err(TinyKVError::ValidationFailed(key.to_string()))
+            }
+            _ => Ok(()),
         }
+    }
 
-        let json = self.serialize_data()?;
-        let temp_path = self.path.with_extension("tmp");
-        fs::write(&temp_path, json)?;
-        fs::rename(&temp_path, &self.path)?;
+    /// Sets a namespace prefix for all keys.
+    /// Keys will be automatically prefixed when stored and accessed.
+    /// Replaces any namespace set earlier; use [`push_namespace`](Self::push_namespace) to nest.
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = Self::namespace_prefix(namespace);
+        self
+    }
 
-        Ok(())
+    /// Nests `namespace` under the current one, so
+    /// `with_namespace("a").push_namespace("b")` prefixes keys with `a:b:`.
+    pub fn push_namespace(mut self, namespace: &str) -> Self {
+        let prefix = Self::namespace_prefix(namespace);
+        self.namespace.push_str(&prefix);
+        self
     }
 
-    /// Removes all expired entries from memory.
-    /// TTL checking only available with `std` feature.
-    pub fn purge_expired(&mut self) -> Result<usize, TinyKVError> {
-        if self.data.is_empty() {
-            return Ok(0);
+    /// Borrows the store as a child scope whose keys are prefixed with the current
+    /// namespace plus `segment`, so on an `app1` store `sub("user")` reads and writes
+    /// `app1:user:*`. A trailing `:` on `segment` is optional. Views nest with `sub`.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn sub(&mut self, segment: &str) -> NamespacedView<'_> {
+        let namespace = format!("{}{}", self.namespace, Self::namespace_prefix(segment));
+        NamespacedView {
+            kv: self,
+            namespace,
         }
+    }
 
-        #[cfg(any(feature = "std", feature = "wasm"))]
-        {
-            let now = Self::current_timestamp()?;
-            let before = self.data.len();
-            self.data.retain(|_, entry| match entry.expires_at {
-                Some(expiry) => now <= expiry,
-                None => true,
-            });
-
-            let removed = before - self.data.len();
+    /// Helper function to normalize a namespace into its `ns:` key prefix.
+    fn namespace_prefix(namespace: &str) -> String {
+        if namespace.is_empty() {
+            String::new()
+        } else if namespace.ends_with(':') {
+            namespace.to_string()
+        } else {
+            format!("{}:", namespace)
+        }
+    }
 
-            if removed > 0 && self.auto_save {
-                #[cfg(feature = "std")]
-                self.save()?;
-                #[cfg(feature = "wasm")]
-                self.web_save()?;
-            }
+    /// Helper function to run `f` with `namespace` in place of the store's default.
+    #[cfg(any(feature = "std", feature = "nanoserde"))]
+    fn in_namespace<R>(&mut self, namespace: &str, f: impl FnOnce(&mut Self) -> R) -> R {
+        let default = core::mem::replace(&mut self.namespace, Self::namespace_prefix(namespace));
+        let result = f(self);
+        self.namespace = default;
+        result
+    }
 
-            Ok(removed)
+    /// Helper function to add namespace prefix to a key.
+    fn namespaced_key(&self, key: &str) -> String {
+        if self.namespace.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}{}", self.namespace, key)
         }
-
-        #[cfg(all(not(feature = "std"), not(feature = "wasm")))]
-        Ok(0) // No TTL support in no_std
     }
 
-    /// Clears all entries from memory.
-    pub fn clear(&mut self) -> Result<(), TinyKVError> {
-        self.data.clear();
+    /// Helper function to tell reserved metadata keys apart from user keys.
+    fn is_reserved_key(key: &str) -> bool {
+        key == VERSION_KEY
+    }
 
-        if self.auto_save {
-            #[cfg(feature = "std")]
-            self.save()?;
-            #[cfg(feature = "wasm")]
-            self.web_save()?;
+    /// Helper function to reject user writes to reserved metadata keys.
+    #[cfg(any(feature = "std", feature = "nanoserde"))]
+    fn check_user_key(&self, key: &str) -> Result<(), TinyKVError> {
+        if Self::is_reserved_key(&self.namespaced_key(key)) {
+            return Err(TinyKVError::ReservedKey(key.to_string()));
         }
-
         Ok(())
     }
 
-    /// Removes all entries that start with the given prefix.
-    pub fn clear_prefix(&mut self, prefix: &str) -> Result<usize, TinyKVError> {
-        let before_count = self.data.len();
-        
-        self.data.retain(|key, _| !key.starts_with(prefix));
+    /// Helper function to remove namespace prefix from a key.
+    fn strip_namespace(&self, key: &str) -> String {
+        if self.namespace.is_empty() {
+            key.to_string()
+        } else if key.starts_with(&self.namespace) {
+            key[self.namespace.len()..].to_string()
+        } else {
+            key.to_string()
+        }
+    }
+
+    #[cfg(feature = "wasm")]
+    fn web_load(&mut self) -> Result<(), TinyKVError> {
+        self.load_from_localstorage()
+    }
+
+    #[cfg(feature = "wasm")]
+    fn web_save(&self) -> Result<(), TinyKVError> {
+        self.save_to_localstorage()
+    }
+
+    #[cfg(feature = "wasm")]
+    fn load_from_localstorage(&mut self) -> Result<(), TinyKVError> {
+        let data_key = format!("{}:data", self.web_prefix);
+
+        if let Some(json_data) = wasm::ls_get_item(&data_key) {
+            let data = Self::deserialize_data(&json_data)?;
+            self.data = data;
+            self.refresh_ttl_flag();
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "wasm")]
+    fn save_to_localstorage(&self) -> Result<(), TinyKVError> {
+        let data_key = format!("{}:data", self.web_prefix);
+        let json_data = self.serialize_data()?;
+
+        wasm::ls_set_item(&data_key, &json_data);
+        Ok(())
+    }
+
+    // Helper method for serialization
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    fn serialize_data(&self) -> Result<String, TinyKVError> {
+        self.codec.serialize(&self.data)
+    }
+
+    #[cfg(feature = "nanoserde")]
+    fn serialize_data(&self) -> Result<String, TinyKVError> {
+        Ok(self.data.serialize_json())
+    }
+
+    #[cfg(all(not(feature = "nanoserde"), not(feature = "std"), feature = "wasm"))]
+    fn deserialize_data(contents: &str) -> Result<HashMap<String, Entry>, TinyKVError> {
+        if contents.trim().is_empty() {
+            return Ok(HashMap::new());
+        }
+        json::parse_store(contents)
+    }
+
+    #[cfg(all(not(feature = "nanoserde"), not(feature = "std"), feature = "wasm"))]
+    fn serialize_data(&self) -> Result<String, TinyKVError> {
+        Ok(json::write_store(&self.data))
+    }
+
+    #[cfg(all(not(feature = "nanoserde"), not(feature = "std"), feature = "wasm"))]
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), TinyKVError> {
+        self.data.insert(
+            key.to_string(),
+            Entry {
+                value: value.to_string(),
+                expires_at: None,
+            },
+        );
+
+        if self.auto_save {
+            self.web_save()?;
+        }
+        Ok(())
+    }
+
+    #[cfg(all(not(feature = "nanoserde"), not(feature = "std"), feature = "wasm"))]
+    pub fn set_with_ttl(
+        &mut self,
+        key: &str,
+        value: &str,
+        ttl_secs: u64,
+    ) -> Result<(), TinyKVError> {
+        let expires_at = Some(Self::current_timestamp()? + ttl_secs);
+
+        self.data.insert(
+            key.to_string(),
+            Entry {
+                value: value.to_string(),
+                expires_at,
+            },
+        );
+        self.may_have_ttl = true;
+
+        if self.auto_save {
+            self.web_save()?;
+        }
+        Ok(())
+    }
+
+    #[cfg(all(not(feature = "nanoserde"), not(feature = "std"), feature = "wasm"))]
+    pub fn get(&self, key: &str) -> Option<String> {
+        let now = Self::current_timestamp().unwrap_or(0);
+
+        if let Some(entry) = self.data.get(key) {
+            if let Some(expiry) = entry.expires_at {
+                if now > expiry {
+                    return None;
+                }
+            }
+            return Some(entry.value.clone());
+        }
+        None
+    }
+
+    #[cfg(all(
+        not(feature = "nanoserde"),
+        not(feature = "std"),
+        not(feature = "wasm")
+    ))]
+    fn serialize_data(&self) -> Result<String, TinyKVError> {
+        Ok(json::write_store(&self.data))
+    }
+
+    /// Helper function to parse store text with the configured codec.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    fn decode_data(&self, contents: &str) -> Result<HashMap<String, Entry>, TinyKVError> {
+        if self.strict_load && !self.custom_codec {
+            return Self::deserialize_data_strict(contents);
+        }
+        self.codec.deserialize(contents)
+    }
+
+    #[cfg(all(
+        not(all(not(feature = "nanoserde"), feature = "std")),
+        any(feature = "std", feature = "wasm")
+    ))]
+    fn decode_data(&self, contents: &str) -> Result<HashMap<String, Entry>, TinyKVError> {
+        Self::deserialize_data(contents)
+    }
+
+    #[cfg(all(not(feature = "std"), not(feature = "wasm")))]
+    fn decode_data(&self, contents: &str) -> Result<BTreeMap<String, Entry>, TinyKVError> {
+        Self::deserialize_data(contents)
+    }
+
+    // Helper method for deserialization
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub(crate) fn deserialize_data(
+        contents: &str,
+    ) -> Result<HashMap<String, Entry>, TinyKVError> {
+        if contents.trim().is_empty() {
+            return Ok(HashMap::new());
+        }
+        let parsed = if Self::is_pair_array(contents)? {
+            serde_json::from_str::<Vec<(String, Entry)>>(contents)
+                .map(|pairs| pairs.into_iter().collect())
+        } else {
+            serde_json::from_str(contents)
+        };
+        parsed.map_err(|e| TinyKVError::Io(io::Error::new(ErrorKind::InvalidData, e)))
+    }
+
+    /// Like `deserialize_data`, but fails on the first key that appears twice instead of
+    /// keeping its last occurrence.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    fn deserialize_data_strict(contents: &str) -> Result<HashMap<String, Entry>, TinyKVError> {
+        if contents.trim().is_empty() {
+            return Ok(HashMap::new());
+        }
+        if !Self::is_pair_array(contents)? {
+            return serde_json::from_str::<StrictEntries>(contents)
+                .map(|entries| entries.0)
+                .map_err(|e| TinyKVError::Io(io::Error::new(ErrorKind::InvalidData, e)));
+        }
+
+        let pairs = serde_json::from_str::<Vec<(String, Entry)>>(contents)
+            .map_err(|e| TinyKVError::Io(io::Error::new(ErrorKind::InvalidData, e)))?;
+        let mut data = HashMap::with_capacity(pairs.len());
+        for (key, entry) in pairs {
+            if data.contains_key(&key) {
+                return Err(TinyKVError::Serialization(format!("duplicate key `{}`", key)));
+            }
+            data.insert(key, entry);
+        }
+        Ok(data)
+    }
+
+    #[cfg(all(feature = "nanoserde", any(feature = "std", feature = "wasm")))]
+    fn deserialize_data(contents: &str) -> Result<HashMap<String, Entry>, TinyKVError> {
+        if contents.trim().is_empty() {
+            return Ok(HashMap::new());
+        }
+        let parsed = if Self::is_pair_array(contents)? {
+            Vec::<(String, Entry)>::deserialize_json(contents)
+                .map(|pairs| pairs.into_iter().collect())
+        } else {
+            HashMap::<String, Entry>::deserialize_json(contents)
+        };
+        parsed.map_err(|e| TinyKVError::Serialization(e.to_string()))
+    }
+
+    #[cfg(all(feature = "nanoserde", not(feature = "std"), not(feature = "wasm")))]
+    fn deserialize_data(contents: &str) -> Result<BTreeMap<String, Entry>, TinyKVError> {
+        if contents.trim().is_empty() {
+            return Ok(BTreeMap::new());
+        }
+        let parsed = if Self::is_pair_array(contents)? {
+            Vec::<(String, Entry)>::deserialize_json(contents)
+                .map(|pairs| pairs.into_iter().collect())
+        } else {
+            BTreeMap::<String, Entry>::deserialize_json(contents)
+        };
+        parsed.map_err(|e| TinyKVError::Serialization(e.to_string()))
+    }
+
+    #[cfg(all(
+        not(feature = "nanoserde"),
+        not(feature = "std"),
+        not(feature = "wasm")
+    ))]
+    fn deserialize_data(contents: &str) -> Result<BTreeMap<String, Entry>, TinyKVError> {
+        if contents.trim().is_empty() {
+            return Ok(BTreeMap::new());
+        }
+        json::parse_store(contents)
+    }
+
+    /// Helper function to detect the root form by its first non-whitespace character:
+    /// `true` for an array of `[key, entry]` pairs, `false` for the usual object map.
+    /// Any other root is rejected, so pointing the store at an unrelated file yields an
+    /// actionable error.
+    #[cfg(any(feature = "std", feature = "nanoserde"))]
+    fn is_pair_array(contents: &str) -> Result<bool, TinyKVError> {
+        let found = match contents.trim_start().chars().next() {
+            Some('{') | None => return Ok(false),
+            Some('[') => return Ok(true),
+            Some('"') => "string",
+            Some('t' | 'f') => "boolean",
+            Some('n') => "null",
+            Some('-' | '0'..='9') => "number",
+            Some(_) => "non-JSON content",
+        };
+        Err(TinyKVError::Serialization(format!(
+            "expected a TinyKV object map or array of pairs, found {}",
+            found
+        )))
+    }
+
+    /// Helper function to insert a freshly written entry, carrying over the creation
+    /// time and read statistics of the live entry it replaces.
+    #[cfg(any(feature = "std", feature = "nanoserde"))]
+    fn insert_entry(&mut self, namespaced_key: String, mut entry: Entry) {
+        #[cfg(feature = "std")]
+        {
+            self.access_seq += 1;
+            entry.used_seq = self.access_seq;
+            entry.inserted_seq = self.access_seq;
+        }
+        if let Some(previous) = self.data.get(&namespaced_key) {
+            let now = entry.modified_at.unwrap_or(0);
+            if previous.expires_at.is_none_or(|expiry| now <= expiry) {
+                entry.created_at = previous.created_at.or(entry.created_at);
+                entry.accessed_at = previous.accessed_at;
+                entry.hits = previous.hits;
+                #[cfg(feature = "std")]
+                {
+                    entry.inserted_seq = previous.inserted_seq;
+                }
+            }
+        }
+        #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+        {
+            if let Some(cache) = &mut self.read_cache {
+                cache.remove(&namespaced_key);
+            }
+            #[cfg(feature = "compression")]
+            if let Some(threshold) = self.compress_above {
+                entry.compress_above(threshold);
+            }
+        }
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        if entry.expires_at.is_some() {
+            self.may_have_ttl = true;
+        }
+        self.put_entry(namespaced_key, entry);
+    }
+
+    /// Helper function to store `entry` as is, keeping the running TTL count in step.
+    #[cfg(any(feature = "std", feature = "nanoserde"))]
+    fn put_entry(&mut self, namespaced_key: String, entry: Entry) {
+        #[cfg(feature = "std")]
+        let expires_at = entry.expires_at;
+        let _previous = self.data.insert(namespaced_key, entry);
+        #[cfg(feature = "std")]
+        {
+            if _previous.is_some_and(|previous| previous.expires_at.is_some()) {
+                self.ttl_entries = self.ttl_entries.saturating_sub(1);
+            }
+            if let Some(expiry) = expires_at {
+                self.ttl_entries += 1;
+                self.next_ttl_expiry = self.next_ttl_expiry.min(expiry);
+            }
+        }
+    }
+
+    /// Helper function to remove a single entry, keeping the running TTL count and the
+    /// read cache in step.
+    fn remove_entry(&mut self, namespaced_key: &str) -> Option<Entry> {
+        let entry = self.data.remove(namespaced_key)?;
+        #[cfg(feature = "std")]
+        if entry.expires_at.is_some() {
+            self.ttl_entries = self.ttl_entries.saturating_sub(1);
+        }
+        #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+        if let Some(cache) = &mut self.read_cache {
+            cache.remove(namespaced_key);
+        }
+        Some(entry)
+    }
+
+    /// Helper function to iterate over unexpired entries in the current namespace.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    fn live_entries(&self, now: u64) -> impl Iterator<Item = (&String, &Entry)> {
+        self.data.iter().filter(move |(key, entry)| {
+            key.starts_with(&self.namespace)
+                && !Self::is_reserved_key(key)
+                && entry.expires_at.is_none_or(|expiry| now <= expiry)
+        })
+    }
+
+    /// Examines the next key of a snapshot of all keys and removes it if expired; the
+    /// snapshot is retaken once every key in it was visited, so each call is O(1)
+    /// amortized. Returns whether an entry was removed. No-op unless opportunistic purge
+    /// is enabled.
+    #[cfg(any(feature = "std", feature = "wasm"))]
+    fn purge_one_expired(&mut self) -> Result<bool, TinyKVError> {
+        if !self.opportunistic_purge || self.data.is_empty() {
+            return Ok(false);
+        }
+        #[cfg(feature = "std")]
+        if self.read_only {
+            return Ok(false);
+        }
+
+        if self.purge_queue.is_empty() {
+            self.purge_queue = self.data.keys().cloned().collect();
+        }
+        let Some(key) = self.purge_queue.pop() else {
+            return Ok(false);
+        };
+
+        let now = Self::current_timestamp()?;
+        let expired = self
+            .data
+            .get(&key)
+            .is_some_and(|entry| entry.expires_at.is_some_and(|expiry| now > expiry));
+        if expired {
+            self.remove_entry(&key);
+            #[cfg(feature = "std")]
+            if self.appends_records() {
+                self.append_tombstones(&[key])?;
+            }
+        }
+        Ok(expired)
+    }
+
+    #[cfg(feature = "std")]
+    fn current_timestamp() -> Result<u64, TinyKVError> {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| TinyKVError::TimeError)
+            .map(|d| d.as_secs())
+    }
+
+    #[cfg(all(feature = "wasm", not(feature = "std")))]
+    fn current_timestamp() -> Result<u64, TinyKVError> {
+        Ok(wasm::current_timestamp())
+    }
+
+    #[cfg(all(not(feature = "std"), not(feature = "wasm")))]
+    #[allow(dead_code)]
+    fn current_timestamp() -> Result<u64, TinyKVError> {
+        Err(TinyKVError::NoStdUnsupported(
+            "System time not available in no_std".to_string(),
+        ))
+    }
+
+    /// Inserts a key with a value (without expiration).
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn set<T: Serialize>(&mut self, key: &str, value: T) -> Result<(), TinyKVError> {
+        self.ensure_writable()?;
+        self.check_user_key(key)?;
+        let started = Instant::now();
+        self.purge_one_expired()?;
+        let val = serde_json::to_value(value)?;
+        self.validate_value(key, &val)?;
+        let namespaced_key = self.namespaced_key(key);
+        let entry = Entry::new(val, None, Self::current_timestamp().ok());
+        self.insert_entry(namespaced_key, entry);
+
+        #[cfg(feature = "std")]
+        self.persist_write(key)?;
+        #[cfg(feature = "wasm")]
+        if self.auto_save {
+            self.web_save()?;
+        }
+        self.observe(Op::Set, started);
+        Ok(())
+    }
+
+    #[cfg(feature = "nanoserde")]
+    pub fn set<T: SerJson>(&mut self, key: &str, value: T) -> Result<(), TinyKVError> {
+        #[cfg(feature = "std")]
+        self.ensure_writable()?;
+        self.check_user_key(key)?;
+        #[cfg(feature = "std")]
+        let started = Instant::now();
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        self.purge_one_expired()?;
+        let json_str = value.serialize_json();
+        let namespaced_key = self.namespaced_key(key);
+        let entry = Entry::new(json_str, None, Self::current_timestamp().ok());
+        self.insert_entry(namespaced_key, entry);
+
+        #[cfg(feature = "std")]
+        self.persist_write(key)?;
+        #[cfg(feature = "wasm")]
+        if self.auto_save {
+            self.web_save()?;
+        }
+        #[cfg(feature = "std")]
+        self.observe(Op::Set, started);
+        Ok(())
+    }
+
+    #[cfg(all(
+        not(feature = "nanoserde"),
+        not(feature = "std"),
+        not(feature = "wasm")
+    ))]
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), TinyKVError> {
+        let namespaced_key = self.namespaced_key(key);
+        self.data.insert(
+            namespaced_key,
+            Entry {
+                value: value.to_string(),
+                expires_at: None,
+            },
+        );
+        Ok(())
+    }
+
+    /// Inserts the value when `Some`, or removes the key when `None`.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn set_option<T: Serialize>(
+        &mut self,
+        key: &str,
+        value: Option<T>,
+    ) -> Result<(), TinyKVError> {
+        match value {
+            Some(value) => self.set(key, value),
+            None => self.remove(key).map(|_| ()),
+        }
+    }
+
+    #[cfg(feature = "nanoserde")]
+    pub fn set_option<T: SerJson>(
+        &mut self,
+        key: &str,
+        value: Option<T>,
+    ) -> Result<(), TinyKVError> {
+        match value {
+            Some(value) => self.set(key, value),
+            None => self.remove(key).map(|_| ()),
+        }
+    }
+
+    /// Stores an explicit JSON `null` under the key. Unlike `set_option(key, None)` the key
+    /// stays present: `contains_key` is true and `get::<Value>` returns `Some(Value::Null)`.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn set_null(&mut self, key: &str) -> Result<(), TinyKVError> {
+        self.set(key, serde_json::Value::Null)
+    }
+
+    #[cfg(feature = "nanoserde")]
+    pub fn set_null(&mut self, key: &str) -> Result<(), TinyKVError> {
+        self.set(key, None::<u8>)
+    }
+
+    /// Inserts a key with value and expiration (TTL in seconds).
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn set_with_ttl<T: Serialize>(
+        &mut self,
+        key: &str,
+        value: T,
+        ttl_secs: u64,
+    ) -> Result<(), TinyKVError> {
+        self.ensure_writable()?;
+        self.check_user_key(key)?;
+        let started = Instant::now();
+        self.purge_one_expired()?;
+        let val = serde_json::to_value(value)?;
+        self.validate_value(key, &val)?;
+        let expires_at = Some(Self::current_timestamp()? + ttl_secs);
+        let namespaced_key = self.namespaced_key(key);
+
+        let entry = Entry::new(val, expires_at, Self::current_timestamp().ok());
+        self.insert_entry(namespaced_key, entry);
+
+        #[cfg(feature = "std")]
+        self.persist_write(key)?;
+        #[cfg(feature = "wasm")]
+        if self.auto_save {
+            self.web_save()?;
+        }
+        self.observe(Op::Set, started);
+        Ok(())
+    }
+
+    #[cfg(feature = "nanoserde")]
+    pub fn set_with_ttl<T: SerJson>(
+        &mut self,
+        key: &str,
+        value: T,
+        ttl_secs: u64,
+    ) -> Result<(), TinyKVError> {
+        #[cfg(feature = "std")]
+        self.ensure_writable()?;
+        self.check_user_key(key)?;
+        #[cfg(feature = "std")]
+        let started = Instant::now();
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        self.purge_one_expired()?;
+        let json_str = value.serialize_json();
+        let expires_at = Some(Self::current_timestamp()? + ttl_secs);
+        let namespaced_key = self.namespaced_key(key);
+
+        let entry = Entry::new(json_str, expires_at, Self::current_timestamp().ok());
+        self.insert_entry(namespaced_key, entry);
+
+        #[cfg(feature = "std")]
+        self.persist_write(key)?;
+        #[cfg(feature = "wasm")]
+        if self.auto_save {
+            self.web_save()?;
+        }
+        #[cfg(feature = "std")]
+        self.observe(Op::Set, started);
+        Ok(())
+    }
+
+    /// Set a key-value pair with a TTL of `base_ttl` plus a random `0..jitter` seconds,
+    /// so keys written together don't all expire at the same moment.
+    /// Only available with the `rand` feature.
+    #[cfg(all(not(feature = "nanoserde"), feature = "rand"))]
+    pub fn set_with_ttl_jitter<T: Serialize>(
+        &mut self,
+        key: &str,
+        value: T,
+        base_ttl: u64,
+        jitter: u64,
+    ) -> Result<(), TinyKVError> {
+        self.set_with_ttl(key, value, base_ttl + Self::random_jitter(jitter))
+    }
+
+    #[cfg(all(feature = "nanoserde", feature = "rand"))]
+    pub fn set_with_ttl_jitter<T: SerJson>(
+        &mut self,
+        key: &str,
+        value: T,
+        base_ttl: u64,
+        jitter: u64,
+    ) -> Result<(), TinyKVError> {
+        self.set_with_ttl(key, value, base_ttl + Self::random_jitter(jitter))
+    }
+
+    /// Helper function to pick a random offset in `0..jitter` (zero when `jitter` is zero).
+    #[cfg(feature = "rand")]
+    fn random_jitter(jitter: u64) -> u64 {
+        if jitter == 0 {
+            0
+        } else {
+            rand::random_range(0..jitter)
+        }
+    }
+
+    #[cfg(all(
+        not(feature = "nanoserde"),
+        not(feature = "std"),
+        not(feature = "wasm")
+    ))]
+    pub fn set_with_ttl(
+        &mut self,
+        key: &str,
+        value: &str,
+        _ttl_secs: u64,
+    ) -> Result<(), TinyKVError> {
+        // TTL not supported in no_std without time
+        self.set(key, value)
+    }
+
+    /// Inserts many keys without expiration, auto-saving once at the end. All values
+    /// are serialized and validated before any of them is inserted.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn set_many<T: Serialize>(
+        &mut self,
+        entries: impl IntoIterator<Item = (String, T)>,
+    ) -> Result<(), TinyKVError> {
+        self.set_many_with_ttl(entries.into_iter().map(|(key, value)| (key, value, None)))
+    }
+
+    #[cfg(all(feature = "nanoserde", any(feature = "std", feature = "wasm")))]
+    pub fn set_many<T: SerJson>(
+        &mut self,
+        entries: impl IntoIterator<Item = (String, T)>,
+    ) -> Result<(), TinyKVError> {
+        self.set_many_with_ttl(entries.into_iter().map(|(key, value)| (key, value, None)))
+    }
+
+    /// Inserts many keys, each with an optional TTL in seconds, computing expiries
+    /// from a single timestamp and auto-saving once at the end. All values are
+    /// serialized and validated before any of them is inserted.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn set_many_with_ttl<T: Serialize>(
+        &mut self,
+        items: impl IntoIterator<Item = (String, T, Option<u64>)>,
+    ) -> Result<(), TinyKVError> {
+        self.ensure_writable()?;
+        let now = Self::current_timestamp()?;
+        let items = items
+            .into_iter()
+            .map(|(key, value, ttl_secs)| (key, value, ttl_secs.map(|ttl| now + ttl)));
+        self.insert_many(items, now)
+    }
+
+    /// Helper function behind `set_many_with_ttl` and `import_csv_entries`: validates
+    /// every value, then inserts them with their absolute expiries and saves once.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    fn insert_many<T: Serialize>(
+        &mut self,
+        items: impl IntoIterator<Item = (String, T, Option<u64>)>,
+        now: u64,
+    ) -> Result<(), TinyKVError> {
+        let mut entries = Vec::new();
+        for (key, value, expires_at) in items {
+            self.check_user_key(&key)?;
+            let val = serde_json::to_value(value)?;
+            self.validate_value(&key, &val)?;
+            let entry = Entry::new(val, expires_at, Some(now));
+            entries.push((key, entry));
+        }
+        let mut needs_save = false;
+        for (key, entry) in entries {
+            self.insert_entry(self.namespaced_key(&key), entry);
+            needs_save |= self.record_write(&key)?;
+        }
+
+        if needs_save && self.auto_save {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    #[cfg(all(feature = "nanoserde", any(feature = "std", feature = "wasm")))]
+    pub fn set_many_with_ttl<T: SerJson>(
+        &mut self,
+        items: impl IntoIterator<Item = (String, T, Option<u64>)>,
+    ) -> Result<(), TinyKVError> {
+        #[cfg(feature = "std")]
+        self.ensure_writable()?;
+        let now = Self::current_timestamp()?;
+
+        let items: Vec<_> = items.into_iter().collect();
+        for (key, _, _) in &items {
+            self.check_user_key(key)?;
+        }
+
+        #[cfg(feature = "std")]
+        let mut needs_save = false;
+        for (key, value, ttl_secs) in items {
+            let expires_at = ttl_secs.map(|ttl| now + ttl);
+            let entry = Entry::new(value.serialize_json(), expires_at, Some(now));
+            self.insert_entry(self.namespaced_key(&key), entry);
+            #[cfg(feature = "std")]
+            {
+                needs_save |= self.record_write(&key)?;
+            }
+        }
+
+        #[cfg(feature = "std")]
+        if needs_save && self.auto_save {
+            self.save()?;
+        }
+        #[cfg(feature = "wasm")]
+        if self.auto_save {
+            self.web_save()?;
+        }
+        Ok(())
+    }
+
+    /// Inserts a key with value that expires at the given UNIX timestamp (seconds).
+    /// Timestamps already in the past are rejected with `TinyKVError::ExpiryInPast`.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn set_with_expiry<T: Serialize>(
+        &mut self,
+        key: &str,
+        value: T,
+        expires_at: u64,
+    ) -> Result<(), TinyKVError> {
+        self.ensure_writable()?;
+        self.check_user_key(key)?;
+        if expires_at < Self::current_timestamp()? {
+            return Err(TinyKVError::ExpiryInPast(expires_at));
+        }
+        self.purge_one_expired()?;
+        let val = serde_json::to_value(value)?;
+        self.validate_value(key, &val)?;
+        let namespaced_key = self.namespaced_key(key);
+
+        let entry = Entry::new(val, Some(expires_at), Self::current_timestamp().ok());
+        self.insert_entry(namespaced_key, entry);
+
+        self.persist_write(key)?;
+        Ok(())
+    }
+
+    #[cfg(all(feature = "nanoserde", any(feature = "std", feature = "wasm")))]
+    pub fn set_with_expiry<T: SerJson>(
+        &mut self,
+        key: &str,
+        value: T,
+        expires_at: u64,
+    ) -> Result<(), TinyKVError> {
+        #[cfg(feature = "std")]
+        self.ensure_writable()?;
+        self.check_user_key(key)?;
+        if expires_at < Self::current_timestamp()? {
+            return Err(TinyKVError::ExpiryInPast(expires_at));
+        }
+        self.purge_one_expired()?;
+        let json_str = value.serialize_json();
+        let namespaced_key = self.namespaced_key(key);
+
+        let entry = Entry::new(json_str, Some(expires_at), Self::current_timestamp().ok());
+        self.insert_entry(namespaced_key, entry);
+
+        #[cfg(feature = "std")]
+        self.persist_write(key)?;
+        #[cfg(feature = "wasm")]
+        if self.auto_save {
+            self.web_save()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the value for `key`, or on a miss (or expiry) computes it with `f`, stores it
+    /// with the given TTL (`None` never expires) and returns it (cache-aside).
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn get_or_compute<T, F>(
+        &mut self,
+        key: &str,
+        ttl: Option<u64>,
+        f: F,
+    ) -> Result<T, TinyKVError>
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+        F: FnOnce() -> T,
+    {
+        if let Some(value) = self.get(key)? {
+            return Ok(value);
+        }
+
+        let value = f();
+        match ttl {
+            Some(ttl_secs) => self.set_with_ttl(key, &value, ttl_secs)?,
+            None => self.set(key, &value)?,
+        }
+        Ok(value)
+    }
+
+    /// Returns the value for `key`, or on a miss (or expiry) stores `f()` without a TTL
+    /// and returns it. Shorthand for `get_or_compute(key, None, f)`.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn get_or_insert_with<T, F>(&mut self, key: &str, f: F) -> Result<T, TinyKVError>
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+        F: FnOnce() -> T,
+    {
+        self.get_or_compute(key, None, f)
+    }
+
+    /// Deserializes the value stored under `key`, mutates it with `f` and stores it back,
+    /// keeping the entry's expiry. Returns `false` without calling `f` if the key is absent
+    /// or expired.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn update<T, F>(&mut self, key: &str, f: F) -> Result<bool, TinyKVError>
+    where
+        T: Serialize + for<'de> Deserialize<'de>,
+        F: FnOnce(&mut T),
+    {
+        self.ensure_writable()?;
+        self.check_user_key(key)?;
+        let now = Self::current_timestamp()?;
+        let namespaced_key = self.namespaced_key(key);
+
+        let (mut value, expires_at) = match self.data.get(&namespaced_key) {
+            Some(entry) if entry.expires_at.is_none_or(|expiry| now <= expiry) => {
+                (self.parse_entry::<T>(key, entry)?, entry.expires_at)
+            }
+            _ => return Ok(false),
+        };
+
+        f(&mut value);
+        let val = serde_json::to_value(value)?;
+        self.validate_value(key, &val)?;
+        let entry = Entry::new(val, expires_at, Some(now));
+        self.insert_entry(namespaced_key, entry);
+
+        self.persist_write(key)?;
+        Ok(true)
+    }
+
+    /// Adds `delta` to the integer stored under `key` (absent or expired keys count as 0),
+    /// stores the result keeping any TTL, and returns it. Fails with
+    /// `TinyKVError::Serialization` if the stored value is not an integer or the sum overflows.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn increment(&mut self, key: &str, delta: i64) -> Result<i64, TinyKVError> {
+        self.ensure_writable()?;
+        self.check_user_key(key)?;
+        let now = Self::current_timestamp()?;
+        let namespaced_key = self.namespaced_key(key);
+
+        let (current, expires_at) = match self.data.get(&namespaced_key) {
+            Some(entry) if entry.expires_at.is_none_or(|expiry| now <= expiry) => {
+                let current = entry.decoded_value()?.as_i64().ok_or_else(|| {
+                    TinyKVError::Serialization(format!("value for `{key}` is not an integer"))
+                })?;
+                (current, entry.expires_at)
+            }
+            _ => (0, None),
+        };
+
+        let total = current.checked_add(delta).ok_or_else(|| {
+            TinyKVError::Serialization(format!("counter `{key}` overflowed"))
+        })?;
+        let val = serde_json::Value::from(total);
+        self.validate_value(key, &val)?;
+        let entry = Entry::new(val, expires_at, Some(now));
+        self.insert_entry(namespaced_key, entry);
+
+        self.persist_write(key)?;
+        Ok(total)
+    }
+
+    /// Subtracts `delta` from the integer stored under `key`; see `increment`.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn decrement(&mut self, key: &str, delta: i64) -> Result<i64, TinyKVError> {
+        let delta = delta.checked_neg().ok_or_else(|| {
+            TinyKVError::Serialization(format!("counter `{key}` overflowed"))
+        })?;
+        self.increment(key, delta)
+    }
+
+    /// Inserts a key with a value (without expiration) and returns the previous value,
+    /// or `None` if the key was absent or expired, like `HashMap::insert`.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn replace<T: Serialize, U: for<'de> Deserialize<'de>>(
+        &mut self,
+        key: &str,
+        value: T,
+    ) -> Result<Option<U>, TinyKVError> {
+        self.ensure_writable()?;
+        self.check_user_key(key)?;
+        self.purge_one_expired()?;
+        let val = serde_json::to_value(value)?;
+        self.validate_value(key, &val)?;
+        let now = Self::current_timestamp()?;
+        let namespaced_key = self.namespaced_key(key);
+
+        let previous = match self.data.get(&namespaced_key) {
+            Some(entry) if entry.expires_at.is_none_or(|expiry| now <= expiry) => {
+                Some(U::deserialize(entry.decoded_value()?.as_ref())?)
+            }
+            _ => None,
+        };
+
+        let entry = Entry::new(val, None, Self::current_timestamp().ok());
+        self.insert_entry(namespaced_key, entry);
+
+        self.persist_write(key)?;
+        Ok(previous)
+    }
+
+    #[cfg(feature = "nanoserde")]
+    pub fn replace<T: SerJson, U: DeJson>(
+        &mut self,
+        key: &str,
+        value: T,
+    ) -> Result<Option<U>, TinyKVError> {
+        #[cfg(feature = "std")]
+        self.ensure_writable()?;
+        self.check_user_key(key)?;
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        self.purge_one_expired()?;
+        let json_str = value.serialize_json();
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        let now = Self::current_timestamp()?;
+        let namespaced_key = self.namespaced_key(key);
+
+        let previous = match self.data.get(&namespaced_key) {
+            #[cfg(any(feature = "std", feature = "wasm"))]
+            Some(entry) if entry.expires_at.is_some_and(|expiry| now > expiry) => None,
+            Some(entry) => Some(
+                U::deserialize_json(&entry.value)
+                    .map_err(|e| TinyKVError::Serialization(e.to_string()))?,
+            ),
+            None => None,
+        };
+
+        let entry = Entry::new(json_str, None, Self::current_timestamp().ok());
+        self.insert_entry(namespaced_key, entry);
+
+        #[cfg(feature = "std")]
+        self.persist_write(key)?;
+        #[cfg(feature = "wasm")]
+        if self.auto_save {
+            self.web_save()?;
+        }
+        Ok(previous)
+    }
+
+    /// Retrieves the value for a given key if it exists and hasn't expired.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn get<T: for<'de> Deserialize<'de>>(
+        &mut self,
+        key: &str,
+    ) -> Result<Option<T>, TinyKVError> {
+        let started = Instant::now();
+        if self.purge_one_expired()? && self.auto_save {
+            self.save()?;
+        }
+        let now = Self::current_timestamp()?;
+        let namespaced_key = self.namespaced_key(key);
+
+        if let Some(entry) = self.data.get(&namespaced_key) {
+            if let Some(expiry) = entry.expires_at
+                && now > expiry
+            {
+                if self.read_only {
+                    self.observe(Op::Get, started);
+                    return Ok(None);
+                }
+                self.remove_entry(&namespaced_key);
+                #[cfg(feature = "std")]
+                self.persist_removals(&[namespaced_key])?;
+                #[cfg(feature = "wasm")]
+                if self.auto_save {
+                    self.web_save()?;
+                }
+                log_op!(debug, "tinykv: expired {}", self.log_key(key));
+                #[cfg(feature = "std")]
+                self.notify_change(key, ChangeKind::Expired);
+                self.observe(Op::Get, started);
+                return Ok(None);
+            }
+
+            let value = self.parse_entry(key, entry)?;
+            if let Some(entry) = self.data.get_mut(&namespaced_key) {
+                entry.accessed_at = Some(now);
+                entry.hits += 1;
+                self.access_seq += 1;
+                entry.used_seq = self.access_seq;
+            }
+            self.observe(Op::Get, started);
+            return Ok(Some(value));
+        }
+
+        log_op!(trace, "tinykv: get miss {}", self.log_key(key));
+        self.observe(Op::Get, started);
+        Ok(None)
+    }
+
+    /// Read-only `get`: returns `None` for an expired key but leaves it in the map, and
+    /// doesn't record access time or hits, so it can be called through a shared reference
+    /// (e.g. behind an `RwLock` read guard). Expired entries linger until a mutating call
+    /// such as `get` or `purge_expired` removes them.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn peek<T: for<'de> Deserialize<'de>>(&self, key: &str) -> Result<Option<T>, TinyKVError> {
+        let now = Self::current_timestamp()?;
+        match self.data.get(&self.namespaced_key(key)) {
+            Some(entry) if entry.expires_at.is_none_or(|expiry| now <= expiry) => {
+                self.parse_entry(key, entry).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Like `get`, but fails with `TinyKVError::KeyNotFound` if the key is absent or expired.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn get_required<T: for<'de> Deserialize<'de>>(
+        &mut self,
+        key: &str,
+    ) -> Result<T, TinyKVError> {
+        self.get(key)?.ok_or_else(|| TinyKVError::KeyNotFound(key.to_string()))
+    }
+
+    #[cfg(feature = "nanoserde")]
+    pub fn get_required<T: DeJson>(&mut self, key: &str) -> Result<T, TinyKVError> {
+        self.get(key)?.ok_or_else(|| TinyKVError::KeyNotFound(key.to_string()))
+    }
+
+    /// Retrieves several keys at once, in input order, with `None` for missing or expired
+    /// keys. Expired keys encountered are removed and auto-saved once at the end.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn get_many<T: for<'de> Deserialize<'de>>(
+        &mut self,
+        keys: &[&str],
+    ) -> Result<Vec<Option<T>>, TinyKVError> {
+        let now = Self::current_timestamp()?;
+
+        let mut purged = Vec::new();
+        let mut values = Vec::with_capacity(keys.len());
+        for key in keys {
+            let namespaced_key = self.namespaced_key(key);
+            let value = match self.data.get(&namespaced_key) {
+                Some(entry) if entry.expires_at.is_some_and(|expiry| now > expiry) => {
+                    if !self.read_only {
+                        self.remove_entry(&namespaced_key);
+                        log_op!(debug, "tinykv: expired {}", self.log_key(key));
+                        self.notify_change(key, ChangeKind::Expired);
+                        purged.push(namespaced_key);
+                    }
+                    None
+                }
+                Some(entry) => {
+                    let value = self.parse_entry(key, entry)?;
+                    if let Some(entry) = self.data.get_mut(&namespaced_key) {
+                        entry.accessed_at = Some(now);
+                        entry.hits += 1;
+                        self.access_seq += 1;
+                        entry.used_seq = self.access_seq;
+                    }
+                    Some(value)
+                }
+                None => None,
+            };
+            values.push(value);
+        }
+
+        self.persist_removals(&purged)?;
+        Ok(values)
+    }
+
+    /// Retrieves several keys at once, reporting each key's outcome separately, so a
+    /// missing or malformed value doesn't fail the whole batch.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn get_many_results<T: for<'de> Deserialize<'de>>(
+        &mut self,
+        keys: &[&str],
+    ) -> Vec<(String, Result<Option<T>, TinyKVError>)> {
+        keys.iter()
+            .map(|key| (key.to_string(), self.get(key)))
+            .collect()
+    }
+
+    #[cfg(feature = "nanoserde")]
+    pub fn get_many_results<T: DeJson>(
+        &mut self,
+        keys: &[&str],
+    ) -> Vec<(String, Result<Option<T>, TinyKVError>)> {
+        keys.iter()
+            .map(|key| (key.to_string(), self.get(key)))
+            .collect()
+    }
+
+    /// Like `get`, but returns a shared handle and, with `with_read_cache` enabled, reuses
+    /// the previously deserialized value while the stored JSON is unchanged.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn get_cached<T>(&mut self, key: &str) -> Result<Option<Arc<T>>, TinyKVError>
+    where
+        T: for<'de> Deserialize<'de> + Send + Sync + 'static,
+    {
+        let now = Self::current_timestamp()?;
+        let namespaced_key = self.namespaced_key(key);
+
+        let hash = match (&self.read_cache, self.data.get(&namespaced_key)) {
+            (Some(_), Some(entry)) if entry.expires_at.is_none_or(|expiry| now <= expiry) => {
+                let mut hasher = DefaultHasher::new();
+                entry.value.hash(&mut hasher);
+                hasher.finish()
+            }
+            _ => return Ok(self.get::<T>(key)?.map(Arc::new)),
+        };
+
+        let cached = self
+            .read_cache
+            .as_ref()
+            .and_then(|cache| cache.get(&namespaced_key))
+            .filter(|(cached_hash, _)| *cached_hash == hash)
+            .and_then(|(_, value)| Arc::clone(value).downcast::<T>().ok());
+        if let Some(value) = cached {
+            if let Some(entry) = self.data.get_mut(&namespaced_key) {
+                entry.accessed_at = Some(now);
+                entry.hits += 1;
+                self.access_seq += 1;
+                entry.used_seq = self.access_seq;
+            }
+            return Ok(Some(value));
+        }
+
+        let Some(value) = self.get::<T>(key)?.map(Arc::new) else {
+            return Ok(None);
+        };
+        if let Some(cache) = &mut self.read_cache {
+            cache.insert(namespaced_key, (hash, value.clone()));
+        }
+        Ok(Some(value))
+    }
+
+    #[cfg(feature = "nanoserde")]
+    pub fn get<T: DeJson>(&mut self, key: &str) -> Result<Option<T>, TinyKVError> {
+        #[cfg(feature = "std")]
+        let started = Instant::now();
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        if self.purge_one_expired()? && self.auto_save {
+            #[cfg(feature = "std")]
+            self.save()?;
+            #[cfg(feature = "wasm")]
+            self.web_save()?;
+        }
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        let now = Self::current_timestamp()?;
+        let namespaced_key = self.namespaced_key(key);
+
+        if let Some(entry) = self.data.get(&namespaced_key) {
+            #[cfg(any(feature = "std", feature = "wasm"))]
+            if let Some(expiry) = entry.expires_at {
+                if now > expiry {
+                    #[cfg(feature = "std")]
+                    if self.read_only {
+                        self.observe(Op::Get, started);
+                        return Ok(None);
+                    }
+                    self.remove_entry(&namespaced_key);
+                    #[cfg(feature = "std")]
+                    self.persist_removals(&[namespaced_key])?;
+                    #[cfg(feature = "wasm")]
+                    if self.auto_save {
+                        self.web_save()?;
+                    }
+                    log_op!(debug, "tinykv: expired {}", self.log_key(key));
+                    #[cfg(feature = "std")]
+                    self.notify_change(key, ChangeKind::Expired);
+                    #[cfg(feature = "std")]
+                    self.observe(Op::Get, started);
+                    return Ok(None);
+                }
+            }
+
+            let value = T::deserialize_json(&entry.value)
+                .map_err(|e| TinyKVError::Serialization(e.to_string()))?;
+            if let Some(entry) = self.data.get_mut(&namespaced_key) {
+                #[cfg(any(feature = "std", feature = "wasm"))]
+                {
+                    entry.accessed_at = Some(now);
+                }
+                entry.hits += 1;
+                #[cfg(feature = "std")]
+                {
+                    self.access_seq += 1;
+                    entry.used_seq = self.access_seq;
+                }
+            }
+            #[cfg(feature = "std")]
+            self.observe(Op::Get, started);
+            return Ok(Some(value));
+        }
+
+        log_op!(trace, "tinykv: get miss {}", self.log_key(key));
+        #[cfg(feature = "std")]
+        self.observe(Op::Get, started);
+        Ok(None)
+    }
+
+    #[cfg(all(
+        not(feature = "nanoserde"),
+        not(feature = "std"),
+        not(feature = "wasm")
+    ))]
+    pub fn get(&self, key: &str) -> Option<String> {
+        let namespaced_key = self.namespaced_key(key);
+        self.data.get(&namespaced_key).map(|entry| entry.value.clone())
+    }
+
+    /// Like `get`, but returns `None` on any failure (missing, expired,
+    /// deserialization or time errors) instead of an error.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn try_get<T: for<'de> Deserialize<'de>>(&mut self, key: &str) -> Option<T> {
+        self.get(key).ok().flatten()
+    }
+
+    #[cfg(feature = "nanoserde")]
+    pub fn try_get<T: DeJson>(&mut self, key: &str) -> Option<T> {
+        self.get(key).ok().flatten()
+    }
+
+    /// Inserts a key into an explicit namespace for this call only,
+    /// ignoring the store's default namespace.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn set_in<T: Serialize>(
+        &mut self,
+        namespace: &str,
+        key: &str,
+        value: T,
+    ) -> Result<(), TinyKVError> {
+        self.in_namespace(namespace, |kv| kv.set(key, value))
+    }
+
+    #[cfg(feature = "nanoserde")]
+    pub fn set_in<T: SerJson>(
+        &mut self,
+        namespace: &str,
+        key: &str,
+        value: T,
+    ) -> Result<(), TinyKVError> {
+        self.in_namespace(namespace, |kv| kv.set(key, value))
+    }
+
+    /// Retrieves a key from an explicit namespace for this call only,
+    /// ignoring the store's default namespace.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn get_in<T: for<'de> Deserialize<'de>>(
+        &mut self,
+        namespace: &str,
+        key: &str,
+    ) -> Result<Option<T>, TinyKVError> {
+        self.in_namespace(namespace, |kv| kv.get(key))
+    }
+
+    #[cfg(feature = "nanoserde")]
+    pub fn get_in<T: DeJson>(
+        &mut self,
+        namespace: &str,
+        key: &str,
+    ) -> Result<Option<T>, TinyKVError> {
+        self.in_namespace(namespace, |kv| kv.get(key))
+    }
+
+    /// Removes a key from the store.
+    pub fn remove(&mut self, key: &str) -> Result<bool, TinyKVError> {
+        #[cfg(feature = "std")]
+        self.ensure_writable()?;
+        let namespaced_key = self.namespaced_key(key);
+        let removed = self.remove_entry(&namespaced_key).is_some();
+
+        #[cfg(feature = "std")]
+        if removed {
+            self.notify_change(key, ChangeKind::Removed);
+            self.persist_removals(&[namespaced_key])?;
+        }
+        #[cfg(feature = "wasm")]
+        if removed && self.auto_save {
+            self.web_save()?;
+        }
+
+        Ok(removed)
+    }
+
+    /// Inserts a key with a value (without expiration), failing with
+    /// `TinyKVError::KeyExists` instead of overwriting a live entry.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn insert_new<T: Serialize>(&mut self, key: &str, value: T) -> Result<(), TinyKVError> {
+        if self.contains_key(key) {
+            return Err(TinyKVError::KeyExists(key.to_string()));
+        }
+        self.set(key, value)
+    }
+
+    #[cfg(feature = "nanoserde")]
+    pub fn insert_new<T: SerJson>(&mut self, key: &str, value: T) -> Result<(), TinyKVError> {
+        if self.contains_key(key) {
+            return Err(TinyKVError::KeyExists(key.to_string()));
+        }
+        self.set(key, value)
+    }
+
+    /// Inserts a value under any key convertible to `StoreKey`, such as a `u64` id.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn set_by<K: Into<StoreKey>, T: Serialize>(
+        &mut self,
+        key: K,
+        value: T,
+    ) -> Result<(), TinyKVError> {
+        self.set(key.into().as_str(), value)
+    }
+
+    #[cfg(feature = "nanoserde")]
+    pub fn set_by<K: Into<StoreKey>, T: SerJson>(
+        &mut self,
+        key: K,
+        value: T,
+    ) -> Result<(), TinyKVError> {
+        self.set(key.into().as_str(), value)
+    }
+
+    /// Retrieves the value stored under a key convertible to `StoreKey`.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn get_by<K: Into<StoreKey>, T: for<'de> Deserialize<'de>>(
+        &mut self,
+        key: K,
+    ) -> Result<Option<T>, TinyKVError> {
+        self.get(key.into().as_str())
+    }
+
+    #[cfg(feature = "nanoserde")]
+    pub fn get_by<K: Into<StoreKey>, T: DeJson>(
+        &mut self,
+        key: K,
+    ) -> Result<Option<T>, TinyKVError> {
+        self.get(key.into().as_str())
+    }
+
+    /// Removes the entry stored under a key convertible to `StoreKey`.
+    pub fn remove_by<K: Into<StoreKey>>(&mut self, key: K) -> Result<bool, TinyKVError> {
+        self.remove(key.into().as_str())
+    }
+
+    /// Removes a key and returns its value, or `None` if it was absent or expired.
+    /// The entry is left in place if its value fails to deserialize as `T`.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn remove_returning<T: for<'de> Deserialize<'de>>(
+        &mut self,
+        key: &str,
+    ) -> Result<Option<T>, TinyKVError> {
+        self.ensure_writable()?;
+        let now = Self::current_timestamp()?;
+        let namespaced_key = self.namespaced_key(key);
+
+        let value = match self.data.get(&namespaced_key) {
+            Some(entry) if entry.expires_at.is_none_or(|expiry| now <= expiry) => {
+                Some(T::deserialize(entry.decoded_value()?.as_ref())?)
+            }
+            Some(_) => None,
+            None => return Ok(None),
+        };
+
+        self.remove(key)?;
+        Ok(value)
+    }
+
+    #[cfg(all(feature = "nanoserde", any(feature = "std", feature = "wasm")))]
+    pub fn remove_returning<T: DeJson>(&mut self, key: &str) -> Result<Option<T>, TinyKVError> {
+        #[cfg(feature = "std")]
+        self.ensure_writable()?;
+        let now = Self::current_timestamp()?;
+        let namespaced_key = self.namespaced_key(key);
+
+        let value = match self.data.get(&namespaced_key) {
+            Some(entry) if entry.expires_at.is_none_or(|expiry| now <= expiry) => Some(
+                T::deserialize_json(&entry.value)
+                    .map_err(|e| TinyKVError::Serialization(e.to_string()))?,
+            ),
+            Some(_) => None,
+            None => return Ok(None),
+        };
+
+        self.remove(key)?;
+        Ok(value)
+    }
+
+    /// Checks if the store contains a given key and it's not expired.
+    pub fn contains_key(&self, key: &str) -> bool {
+        let namespaced_key = self.namespaced_key(key);
+        if let Some(_entry) = self.data.get(&namespaced_key) {
+            #[cfg(any(feature = "std", feature = "wasm"))]
+            if let Some(expiry) = _entry.expires_at {
+                let now = Self::current_timestamp().unwrap_or(0);
+                return now <= expiry;
+            }
+            return true;
+        }
+        false
+    }
+
+    /// Returns a list of all unexpired keys in the store.
+    /// If namespace is set, returns keys with namespace prefix stripped.
+    pub fn keys(&self) -> Vec<String> {
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        let now = Self::current_timestamp().unwrap_or(0);
+
+        self.data
+            .iter()
+            .filter(|(key, _entry)| {
+                // If namespace is set, only include keys from this namespace
+                if !self.namespace.is_empty() && !key.starts_with(&self.namespace) {
+                    return false;
+                }
+                if Self::is_reserved_key(key) {
+                    return false;
+                }
+                
+                // Check expiration
+                #[cfg(any(feature = "std", feature = "wasm"))]
+                match _entry.expires_at {
+                    Some(expiry) => now <= expiry,
+                    None => true,
+                }
+                #[cfg(all(not(feature = "std"), not(feature = "wasm")))]
+                true
+            })
+            .map(|(k, _)| self.strip_namespace(k))
+            .collect()
+    }
+
+    /// Returns every unexpired key in sorted order, as stored (with any namespace prefix),
+    /// regardless of the configured namespace.
+    pub fn all_keys(&self) -> Vec<String> {
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        let now = Self::current_timestamp().unwrap_or(0);
+
+        let mut keys: Vec<String> = self
+            .data
+            .iter()
+            .filter(|(key, _entry)| {
+                if Self::is_reserved_key(key) {
+                    return false;
+                }
+
+                #[cfg(any(feature = "std", feature = "wasm"))]
+                return _entry.expires_at.is_none_or(|expiry| now <= expiry);
+                #[cfg(all(not(feature = "std"), not(feature = "wasm")))]
+                true
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Returns the distinct top-level namespaces in use, sorted: the part of each
+    /// unexpired key before its first `:`. Keys without a `:` aren't counted.
+    pub fn namespaces(&self) -> Vec<String> {
+        let mut namespaces: Vec<String> = self
+            .all_keys()
+            .into_iter()
+            .filter_map(|key| key.split_once(':').map(|(namespace, _)| namespace.to_string()))
+            .collect();
+        namespaces.dedup();
+        namespaces
+    }
+
+    /// Iterates over all unexpired keys without allocating, borrowing them from the store.
+    /// If namespace is set, yields keys from this namespace with the prefix stripped.
+    pub fn keys_ref(&self) -> impl Iterator<Item = &str> {
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        let now = Self::current_timestamp().unwrap_or(0);
+
+        self.data
+            .iter()
+            .filter(move |(key, _entry)| {
+                if !key.starts_with(&self.namespace) || Self::is_reserved_key(key) {
+                    return false;
+                }
+
+                #[cfg(any(feature = "std", feature = "wasm"))]
+                return _entry.expires_at.is_none_or(|expiry| now <= expiry);
+                #[cfg(all(not(feature = "std"), not(feature = "wasm")))]
+                true
+            })
+            .map(|(key, _)| &key[self.namespace.len()..])
+    }
+
+    /// Returns a list of all unexpired keys that start with the given prefix.
+    pub fn list_keys(&self, prefix: &str) -> Vec<String> {
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        let now = Self::current_timestamp().unwrap_or(0);
+
+        self.data
+            .iter()
+            .filter(|(key, _entry)| {
+                // Check prefix
+                if !key.starts_with(prefix) || Self::is_reserved_key(key) {
+                    return false;
+                }
+                
+                // Check expiration
+                #[cfg(any(feature = "std", feature = "wasm"))]
+                match _entry.expires_at {
+                    Some(expiry) => now <= expiry,
+                    None => true,
+                }
+                #[cfg(all(not(feature = "std"), not(feature = "wasm")))]
+                true
+            })
+            .map(|(k, _)| k.clone())
+            .collect()
+    }
+
+    /// Returns unexpired keys written at or after `since` (UNIX seconds), for delta sync.
+    /// Keys are returned with the namespace prefix stripped. Entries written before
+    /// modification times were recorded are never included.
+    #[cfg(any(feature = "std", feature = "wasm"))]
+    pub fn keys_modified_after(&self, since: u64) -> Vec<String> {
+        let now = Self::current_timestamp().unwrap_or(0);
+
+        self.data
+            .iter()
+            .filter(|(key, entry)| {
+                key.starts_with(&self.namespace)
+                    && !Self::is_reserved_key(key)
+                    && entry.expires_at.is_none_or(|expiry| now <= expiry)
+                    && entry.modified_at.is_some_and(|modified| modified >= since)
+            })
+            .map(|(k, _)| self.strip_namespace(k))
+            .collect()
+    }
+
+    /// Returns the unexpired keys whose stored value equals `value` (reverse lookup).
+    /// Keys are returned with the namespace prefix stripped.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn keys_with_value<T: Serialize>(&self, value: &T) -> Result<Vec<String>, TinyKVError> {
+        let target = serde_json::to_value(value)?;
+        let now = Self::current_timestamp()?;
+
+        Ok(self
+            .live_entries(now)
+            .filter(|(_, entry)| entry.decoded_value().is_ok_and(|value| *value == target))
+            .map(|(key, _)| self.strip_namespace(key))
+            .collect())
+    }
+
+    #[cfg(all(feature = "nanoserde", any(feature = "std", feature = "wasm")))]
+    pub fn keys_with_value<T: SerJson>(&self, value: &T) -> Result<Vec<String>, TinyKVError> {
+        let target = value.serialize_json();
+        let now = Self::current_timestamp()?;
+
+        Ok(self
+            .data
+            .iter()
+            .filter(|(key, entry)| {
+                key.starts_with(&self.namespace)
+                    && !Self::is_reserved_key(key)
+                    && entry.expires_at.is_none_or(|expiry| now <= expiry)
+                    && entry.value == target
+            })
+            .map(|(key, _)| self.strip_namespace(key))
+            .collect())
+    }
+
+    /// Returns every unexpired key with its expiry timestamp (`None` if it never expires).
+    /// Keys are returned with the namespace prefix stripped.
+    #[cfg(any(feature = "std", feature = "wasm"))]
+    pub fn all_expiries(&self) -> Vec<(String, Option<u64>)> {
+        let now = Self::current_timestamp().unwrap_or(0);
+
+        self.data
+            .iter()
+            .filter(|(key, entry)| {
+                key.starts_with(&self.namespace)
+                    && !Self::is_reserved_key(key)
+                    && entry.expires_at.is_none_or(|expiry| now <= expiry)
+            })
+            .map(|(k, entry)| (self.strip_namespace(k), entry.expires_at))
+            .collect()
+    }
+
+    /// Returns how long the key has left to live: `Some(Ttl::Expires(secs))` for a key with
+    /// a TTL, `Some(Ttl::None)` for a key that never expires, or `None` if the key is absent
+    /// or already expired. Fails with `NoStdUnsupported` when system time is unavailable.
+    pub fn get_ttl(&self, key: &str) -> Result<Option<Ttl>, TinyKVError> {
+        let now = Self::current_timestamp()?;
+        let namespaced_key = self.namespaced_key(key);
+
+        Ok(match self.data.get(&namespaced_key).map(|entry| entry.expires_at) {
+            None => None,
+            Some(None) => Some(Ttl::None),
+            Some(Some(expiry)) if now <= expiry => Some(Ttl::Expires(expiry - now)),
+            Some(Some(_)) => None,
+        })
+    }
+
+    /// Pushes back the expiry of a key by `additional_secs` without touching its value,
+    /// persisting it like any other single-key write. Returns `false` if the key is absent, already expired or has
+    /// no TTL.
+    #[cfg(any(feature = "std", feature = "wasm"))]
+    pub fn extend_ttl(&mut self, key: &str, additional_secs: u64) -> Result<bool, TinyKVError> {
+        #[cfg(feature = "std")]
+        self.ensure_writable()?;
+        let now = Self::current_timestamp()?;
+        let namespaced_key = self.namespaced_key(key);
+
+        let Some(expiry) = self
+            .data
+            .get_mut(&namespaced_key)
+            .and_then(|entry| entry.expires_at.as_mut())
+            .filter(|expiry| now <= **expiry)
+        else {
+            return Ok(false);
+        };
+        *expiry = expiry.saturating_add(additional_secs);
+
+        #[cfg(feature = "std")]
+        self.persist_write(key)?;
+        #[cfg(feature = "wasm")]
+        if self.auto_save {
+            self.web_save()?;
+        }
+        Ok(true)
+    }
+
+    /// Clears the expiry of a live key so it never expires, persisting it like any other
+    /// single-key write.
+    /// Returns `false` if the key is absent, already expired or had no TTL.
+    #[cfg(any(feature = "std", feature = "wasm"))]
+    pub fn persist(&mut self, key: &str) -> Result<bool, TinyKVError> {
+        #[cfg(feature = "std")]
+        self.ensure_writable()?;
+        let now = Self::current_timestamp()?;
+        let namespaced_key = self.namespaced_key(key);
+
+        let Some(entry) = self
+            .data
+            .get_mut(&namespaced_key)
+            .filter(|entry| entry.expires_at.is_some_and(|expiry| now <= expiry))
+        else {
+            return Ok(false);
+        };
+        entry.expires_at = None;
+        #[cfg(feature = "std")]
+        {
+            self.ttl_entries = self.ttl_entries.saturating_sub(1);
+        }
+
+        #[cfg(feature = "std")]
+        self.persist_write(key)?;
+        #[cfg(feature = "wasm")]
+        if self.auto_save {
+            self.web_save()?;
+        }
+        Ok(true)
+    }
+
+    /// Returns number of unexpired entries. Use `raw_len` for the stored count including
+    /// expired entries that haven't been purged yet.
+    pub fn len(&self) -> usize {
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        if !self.may_have_ttl {
+            return self.data.len() - usize::from(self.data.contains_key(VERSION_KEY));
+        }
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        let now = Self::current_timestamp().unwrap_or(0);
+
+        self.data
+            .iter()
+            .filter(|(key, _entry)| {
+                if Self::is_reserved_key(key) {
+                    return false;
+                }
+
+                #[cfg(any(feature = "std", feature = "wasm"))]
+                match _entry.expires_at {
+                    Some(expiry) => now <= expiry,
+                    None => true,
+                }
+                #[cfg(all(not(feature = "std"), not(feature = "wasm")))]
+                true
+            })
+            .count()
+    }
+
+    /// Returns creation, modification, access and hit metadata for an unexpired key.
+    /// Reads through `get` update the access time and hit count.
+    #[cfg(any(feature = "std", feature = "wasm"))]
+    pub fn entry_metadata(&self, key: &str) -> Option<crate::EntryMeta> {
+        let now = Self::current_timestamp().unwrap_or(0);
+        self.data
+            .get(&self.namespaced_key(key))
+            .filter(|entry| entry.expires_at.is_none_or(|expiry| now <= expiry))
+            .map(Entry::metadata)
+    }
+
+    /// Returns the number of stored entries, including expired ones not yet purged.
+    /// `raw_len() - len()` is the number of dead entries a `purge_expired` would drop.
+    pub fn raw_len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Old name of `raw_len`.
+    #[deprecated(note = "renamed to `raw_len`")]
+    pub fn len_raw(&self) -> usize {
+        self.raw_len()
+    }
+
+    /// Returns true if the store holds no unexpired entries, i.e. `len() == 0`.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns how many unexpired values in the current namespace deserialize as `T`.
+    /// Useful for auditing a heterogeneous store or verifying a migration.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn count_deserializable_as<T: for<'de> Deserialize<'de>>(&self) -> usize {
+        let now = Self::current_timestamp().unwrap_or(0);
+
+        self.live_entries(now)
+            .filter(|(_, entry)| {
+                entry
+                    .decoded_value()
+                    .is_ok_and(|value| T::deserialize(value.as_ref()).is_ok())
+            })
+            .count()
+    }
+
+    /// Deserializes each unexpired entry in the current namespace and hands it to `f` one
+    /// at a time, so large stores can be streamed without collecting them into a `Vec`.
+    /// Keys are passed with the namespace prefix stripped.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn for_each_value<T, F>(&self, mut f: F) -> Result<(), TinyKVError>
+    where
+        T: for<'de> Deserialize<'de>,
+        F: FnMut(String, T),
+    {
+        let now = Self::current_timestamp()?;
+
+        for (key, entry) in self.live_entries(now) {
+            let key = self.strip_namespace(key);
+            let value = self.parse_entry(&key, entry)?;
+            f(key, value);
+        }
+        Ok(())
+    }
+
+    /// Iterates over the unexpired entries in the current namespace, in no particular order,
+    /// with keys stripped of the namespace prefix and values deserialized as `T`. Entries
+    /// that fail to deserialize are skipped (see `try_iter`); expired ones are skipped but
+    /// not removed.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn iter<T: for<'de> Deserialize<'de>>(&self) -> impl Iterator<Item = (String, T)> {
+        self.try_iter().filter_map(Result::ok)
+    }
+
+    /// Like `iter`, but yields an error for each entry that fails to deserialize as `T`.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn try_iter<T: for<'de> Deserialize<'de>>(
+        &self,
+    ) -> impl Iterator<Item = Result<(String, T), TinyKVError>> {
+        let now = Self::current_timestamp().unwrap_or(0);
+
+        self.live_entries(now).map(|(key, entry)| {
+            let key = self.strip_namespace(key);
+            let value = self.parse_entry(&key, entry)?;
+            Ok((key, value))
+        })
+    }
+
+    /// Returns every unexpired value in the current namespace deserialized as `T`, in no
+    /// particular order. Fails with the first value that doesn't deserialize.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn values<T: for<'de> Deserialize<'de>>(&self) -> Result<Vec<T>, TinyKVError> {
+        self.try_iter().map(|item| item.map(|(_, value)| value)).collect()
+    }
+
+    /// Writes every unexpired string-valued entry in the current namespace as a `key,value`
+    /// CSV row, in key order and without a header. Non-string values are skipped.
+    /// Returns the number of rows written. Only available with the `csv` feature.
+    #[cfg(all(not(feature = "nanoserde"), feature = "csv"))]
+    pub fn export_csv<W: Write>(&self, w: W) -> Result<usize, TinyKVError> {
+        let now = Self::current_timestamp()?;
+        let mut rows = Vec::new();
+        for (key, entry) in self.live_entries(now) {
+            if let Some(value) = entry.decoded_value()?.as_str() {
+                rows.push((self.strip_namespace(key), value.to_string()));
+            }
+        }
+        rows.sort();
+
+        let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(w);
+        for (key, value) in &rows {
+            writer.write_record([key, value])?;
+        }
+        writer.flush()?;
+        Ok(rows.len())
+    }
+
+    /// Reads `key,value` CSV rows (no header) and stores each value as a string without
+    /// expiration, auto-saving once. Nothing is inserted if any row fails to parse.
+    /// Returns the number of rows imported. Only available with the `csv` feature.
+    #[cfg(all(not(feature = "nanoserde"), feature = "csv"))]
+    pub fn import_csv<R: Read>(&mut self, r: R) -> Result<usize, TinyKVError> {
+        self.ensure_writable()?;
+        let mut reader = csv::ReaderBuilder::new().has_headers(false).from_reader(r);
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let record = record?;
+            match (record.get(0), record.get(1), record.len()) {
+                (Some(key), Some(value), 2) => rows.push((key.to_string(), value.to_string())),
+                _ => {
+                    return Err(TinyKVError::Serialization(format!(
+                        "expected 2 CSV columns, found {}",
+                        record.len()
+                    )));
+                }
+            }
+        }
+
+        let imported = rows.len();
+        self.set_many_with_ttl(rows.into_iter().map(|(key, value)| (key, value, None)))?;
+        Ok(imported)
+    }
+
+    /// Writes every unexpired entry in the current namespace as a `key,value,expires_at`
+    /// CSV row, in key order and without a header. Unlike `export_csv`, values of any type
+    /// are kept, written as their JSON text, and `expires_at` is the UNIX expiry or empty.
+    /// Returns the number of rows written. Only available with the `csv` feature.
+    #[cfg(all(not(feature = "nanoserde"), feature = "csv"))]
+    pub fn export_csv_entries<W: Write>(&self, w: W) -> Result<usize, TinyKVError> {
+        let now = Self::current_timestamp()?;
+        let mut rows = Vec::new();
+        for (key, entry) in self.live_entries(now) {
+            if Self::is_reserved_key(key) {
+                continue;
+            }
+            let value = serde_json::to_string(&*entry.decoded_value()?)?;
+            let expires_at = entry.expires_at.map(|expiry| expiry.to_string());
+            rows.push((self.strip_namespace(key), value, expires_at.unwrap_or_default()));
+        }
+        rows.sort();
+
+        let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(w);
+        for (key, value, expires_at) in &rows {
+            writer.write_record([key, value, expires_at])?;
+        }
+        writer.flush()?;
+        Ok(rows.len())
+    }
+
+    /// Reads `key,value,expires_at` CSV rows (no header) as written by `export_csv_entries`
+    /// and stores them, auto-saving once. Rows whose expiry has already passed are skipped.
+    /// Nothing is inserted if any row fails to parse. Returns the number of rows imported.
+    /// Only available with the `csv` feature.
+    #[cfg(all(not(feature = "nanoserde"), feature = "csv"))]
+    pub fn import_csv_entries<R: Read>(&mut self, r: R) -> Result<usize, TinyKVError> {
+        self.ensure_writable()?;
+        let now = Self::current_timestamp()?;
+        let mut reader = csv::ReaderBuilder::new().has_headers(false).from_reader(r);
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let record = record?;
+            let (Some(key), Some(value), Some(expires_at), 3) =
+                (record.get(0), record.get(1), record.get(2), record.len())
+            else {
+                return Err(TinyKVError::Serialization(format!(
+                    "expected 3 CSV columns, found {}",
+                    record.len()
+                )));
+            };
+
+            let value: serde_json::Value = serde_json::from_str(value)?;
+            let expires_at = match expires_at {
+                "" => None,
+                expires_at => {
+                    let expiry: u64 = expires_at.parse().map_err(|_| {
+                        TinyKVError::Serialization(format!("invalid expiry `{}`", expires_at))
+                    })?;
+                    if expiry < now {
+                        continue;
+                    }
+                    Some(expiry)
+                }
+            };
+            rows.push((key.to_string(), value, expires_at));
+        }
+
+        let imported = rows.len();
+        self.insert_many(rows, now)?;
+        Ok(imported)
+    }
+
+    /// Returns the next `limit` unexpired entries in sorted key order and advances
+    /// the internal export cursor, so repeated calls page through the whole store.
+    /// Keys are returned with the namespace prefix stripped.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn export_chunk<T: for<'de> Deserialize<'de>>(
+        &mut self,
+        limit: usize,
+    ) -> Result<Vec<(String, T)>, TinyKVError> {
+        let now = Self::current_timestamp()?;
+        let cursor = self.export_cursor.as_deref();
+
+        let mut keys: Vec<&String> = self
+            .live_entries(now)
+            .map(|(key, _)| key)
+            .filter(|key| cursor.is_none_or(|c| key.as_str() > c))
+            .collect();
+        keys.sort();
+        keys.truncate(limit);
+
+        let mut chunk = Vec::with_capacity(keys.len());
+        for key in &keys {
+            let stripped = self.strip_namespace(key);
+            let value = self.parse_entry(&stripped, &self.data[*key])?;
+            chunk.push((stripped, value));
+        }
+
+        if let Some(last) = keys.last() {
+            self.export_cursor = Some((*last).clone());
+        }
+        Ok(chunk)
+    }
+
+    /// Returns the unexpired entries of the current namespace in sorted key order as a
+    /// double-ended iterator, so callers can page from either end. Keys are namespace-stripped;
+    /// values compressed by `with_compress_above` are decompressed, and entries whose value
+    /// can't be decoded are skipped.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn entries_ordered(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = (&str, Cow<'_, serde_json::Value>)> + ExactSizeIterator
+    {
+        let now = Self::current_timestamp().unwrap_or(0);
+        let mut entries: Vec<(&str, Cow<'_, serde_json::Value>)> = self
+            .live_entries(now)
+            .filter_map(|(key, entry)| {
+                let value = entry.decoded_value().ok()?;
+                Some((&key[self.namespace.len()..], value))
+            })
+            .collect();
+        entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        entries.into_iter()
+    }
+
+    /// Returns the unexpired entries in descending key order; see `entries_ordered`.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn entries_rev(&self) -> impl Iterator<Item = (&str, Cow<'_, serde_json::Value>)> {
+        self.entries_ordered().rev()
+    }
+
+    /// Rewinds the export cursor used by `export_chunk` to the beginning.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn reset_export_cursor(&mut self) {
+        self.export_cursor = None;
+    }
+
+    /// Visits every unexpired entry in the current namespace with a mutable copy of
+    /// its value; `f` returns whether it modified the value. All modifications are
+    /// validated first and then applied together, with a single auto-save.
+    /// Returns the number of entries modified.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn for_each_mut<F>(&mut self, mut f: F) -> Result<usize, TinyKVError>
+    where
+        F: FnMut(&str, &mut serde_json::Value) -> bool,
+    {
+        self.ensure_writable()?;
+        let now = Self::current_timestamp()?;
+
+        let mut changes = Vec::new();
+        for (key, entry) in self.live_entries(now) {
+            let stripped = self.strip_namespace(key);
+            let mut value = entry.decoded_value()?.into_owned();
+            if f(&stripped, &mut value) {
+                self.validate_value(&stripped, &value)?;
+                changes.push((key.clone(), stripped, value));
+            }
+        }
+
+        let modified = changes.len();
+        let mut needs_save = false;
+        for (key, stripped, value) in changes {
+            if let Some(entry) = self.data.get_mut(&key) {
+                entry.value = value;
+                entry.compressed = false;
+                entry.modified_at = Some(now);
+                #[cfg(feature = "compression")]
+                if let Some(threshold) = self.compress_above {
+                    entry.compress_above(threshold);
+                }
+                needs_save |= self.record_write(&stripped)?;
+            }
+        }
+
+        if needs_save && self.auto_save {
+            self.save()?;
+        }
+        Ok(modified)
+    }
+
+    /// Save contents to disk. Creates a `.bak` file if backup is enabled.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn save(&self) -> Result<(), TinyKVError> {
+        self.write_file(false).map(|_| ())
+    }
+
+    /// Like `save`, but skips the write (and backup) when the keys, values and expiries hash
+    /// the same as at the last save, so reads and writes that restore a previous value
+    /// don't touch the file. Returns whether the file was written.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn save_if_changed(&self) -> Result<bool, TinyKVError> {
+        self.write_file(true)
+    }
+
+    /// Helper function behind `save` and `save_if_changed`.
+    #[cfg(feature = "std")]
+    fn write_file(&self, skip_unchanged: bool) -> Result<bool, TinyKVError> {
+        self.ensure_writable()?;
+        let started = Instant::now();
+        let hash = self.content_hash();
+        let mut last_saved_hash = self
+            .last_saved_hash
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if skip_unchanged && *last_saved_hash == Some(hash) && self.path.exists() {
+            self.observe(Op::Save, started);
+            return Ok(false);
+        }
+        let json = self.file_contents()?;
+
+        if self.create_dirs
+            && let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            fs::create_dir_all(parent)?;
+        }
+        #[cfg(feature = "file-lock")]
+        let _lock = self.lock_for_io()?;
+
+        if self.backup_enabled && self.path.exists() {
+            if let Some(dir) = &self.backup_dir {
+                fs::create_dir_all(dir)?;
+            }
+            let backup_path = match self.backup_strategy {
+                BackupStrategy::Overwrite => self.single_backup_path(),
+                BackupStrategy::Rotating(count) => {
+                    self.rotate_backups(count)?;
+                    self.rotated_backup_path(1)
+                }
+                BackupStrategy::Timestamped => {
+                    self.timestamped_backup_path(Self::current_timestamp()?)
+                }
+            };
+            fs::copy(&self.path, backup_path)?;
+        }
+
+        let bytes = self.encode_file(json)?;
+        let temp_path = self.path.with_extension("tmp");
+        let mut guard = TempFileGuard::new(&temp_path);
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(&bytes)?;
+        let fsync = self.fsync_due();
+        let fsync_started = Instant::now();
+        if fsync {
+            file.sync_all()?;
+        }
+        drop(file);
+        Self::replace_file(&temp_path, &self.path)?;
+        guard.disarm();
+        if fsync {
+            self.sync_parent_dir()?;
+            self.observe(Op::Fsync, fsync_started);
+        }
+
+        *last_saved_hash = Some(hash);
+        log_op!(debug, "tinykv: saved {} entries to {}", self.data.len(), self.path.display());
+        self.observe(Op::Save, started);
+        Ok(true)
+    }
+
+    /// Helper function to hash the keys, values and expiries for `save_if_changed`, leaving
+    /// out access and modification metadata.
+    #[cfg(feature = "std")]
+    fn content_hash(&self) -> u64 {
+        let mut entries: Vec<_> = self.data.iter().collect();
+        entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        let mut hasher = DefaultHasher::new();
+        for (key, entry) in entries {
+            key.hash(&mut hasher);
+            #[cfg(feature = "nanoserde")]
+            entry.value.hash(&mut hasher);
+            #[cfg(not(feature = "nanoserde"))]
+            {
+                entry.value.to_string().hash(&mut hasher);
+                entry.compressed.hash(&mut hasher);
+            }
+            entry.expires_at.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Copies the `.bak` file (the newest one, with rotating or timestamped backups) over
+    /// the store file and reloads from it. Returns `false` without touching anything if no
+    /// backup exists. Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn restore_from_backup(&mut self) -> Result<bool, TinyKVError> {
+        self.ensure_writable()?;
+        let backup_path = match self.backup_strategy {
+            BackupStrategy::Overwrite => self.single_backup_path(),
+            BackupStrategy::Rotating(_) => self.rotated_backup_path(1),
+            BackupStrategy::Timestamped => match self.timestamped_backups()?.pop() {
+                Some((_, path)) => path,
+                None => return Ok(false),
+            },
+        };
+        if !backup_path.exists() {
+            return Ok(false);
+        }
+
+        self.install_file(&backup_path)?;
+        self.reload()?;
+        Ok(true)
+    }
+
+    /// Loads the store file at `other` and folds its unexpired entries into this store
+    /// according to `strategy`, saving once if auto-save is enabled. Keys are merged as
+    /// stored, across all namespaces. Returns the number of entries taken from `other`.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn merge_file<P: AsRef<Path>>(
+        &mut self,
+        other: P,
+        strategy: MergeStrategy,
+    ) -> Result<usize, TinyKVError> {
+        self.ensure_writable()?;
+        let now = Self::current_timestamp()?;
+        let incoming = self.decode_data(&Self::read_file_text(other.as_ref())?)?;
+
+        let mut merged = 0;
+        for (key, entry) in incoming {
+            if Self::is_reserved_key(&key) || entry.expires_at.is_some_and(|expiry| now > expiry) {
+                continue;
+            }
+            let current = self
+                .data
+                .get(&key)
+                .filter(|current| current.expires_at.is_none_or(|expiry| now <= expiry));
+            let take = match (strategy, current) {
+                (_, None) | (MergeStrategy::Overwrite, _) => true,
+                (MergeStrategy::KeepExisting, Some(_)) => false,
+                (MergeStrategy::Newest, Some(current)) => {
+                    entry.modified_at.unwrap_or(0) > current.modified_at.unwrap_or(0)
+                }
+            };
+            if take {
+                self.insert_entry(key, entry);
+                merged += 1;
+            }
+        }
+
+        if merged > 0 && self.auto_save {
+            self.save()?;
+        }
+        Ok(merged)
+    }
+
+    /// Writes the current state to a new checkpoint file next to the store (or in the
+    /// backup directory, if set) and returns its id, for a later `restore_checkpoint`.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn checkpoint(&mut self) -> Result<CheckpointId, TinyKVError> {
+        self.ensure_writable()?;
+        if !self.is_file_backed() {
+            return Err(TinyKVError::Io(io::Error::new(
+                ErrorKind::Unsupported,
+                "checkpoints require a file-backed store",
+            )));
+        }
+
+        let json = self.file_contents()?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| TinyKVError::TimeError)?;
+        let mut id = CheckpointId(now.as_millis() as u64);
+        while self.checkpoint_path(id).exists() {
+            id.0 += 1;
+        }
+
+        if let Some(dir) = &self.backup_dir {
+            fs::create_dir_all(dir)?;
+        }
+        let bytes = self.encode_file(json)?;
+        #[cfg(feature = "file-lock")]
+        let _lock = self.lock_for_io()?;
+        fs::write(self.checkpoint_path(id), bytes)?;
+        Ok(id)
+    }
+
+    /// Copies the checkpoint file over the store file and reloads from it.
+    /// Returns `false` without touching anything if the checkpoint doesn't exist.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn restore_checkpoint(&mut self, id: CheckpointId) -> Result<bool, TinyKVError> {
+        self.ensure_writable()?;
+        let checkpoint_path = self.checkpoint_path(id);
+        if !checkpoint_path.exists() {
+            return Ok(false);
+        }
+
+        self.install_file(&checkpoint_path)?;
+        self.reload()?;
+        Ok(true)
+    }
+
+    /// Helper function to replace the store file with a copy of `source`: the copy goes to
+    /// a temp file first and is then moved into place while holding the file lock.
+    #[cfg(feature = "std")]
+    fn install_file(&self, source: &Path) -> Result<(), TinyKVError> {
+        let temp_path = self.path.with_extension("tmp");
+        let mut guard = TempFileGuard::new(&temp_path);
+        fs::copy(source, &temp_path)?;
+
+        #[cfg(feature = "file-lock")]
+        let _lock = self.lock_for_io()?;
+        Self::replace_file(&temp_path, &self.path)?;
+        guard.disarm();
+        Ok(())
+    }
+
+    /// Returns the ids of all checkpoints of this store on disk, oldest first.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn checkpoints(&self) -> Result<Vec<CheckpointId>, TinyKVError> {
+        let (dir, prefix) = self.checkpoint_location();
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut ids = Vec::new();
+        for entry in entries {
+            let name = entry?.file_name();
+            if let Some(id) = name
+                .to_str()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|id| id.parse().ok())
+            {
+                ids.push(CheckpointId(id));
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    /// Deletes all but the `keep` most recent checkpoints.
+    /// Returns the number of checkpoints removed. Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn prune_checkpoints(&self, keep: usize) -> Result<usize, TinyKVError> {
+        self.ensure_writable()?;
+        let ids = self.checkpoints()?;
+        let excess = ids.len().saturating_sub(keep);
+        #[cfg(feature = "file-lock")]
+        let _lock = self.lock_for_io()?;
+        for id in &ids[..excess] {
+            fs::remove_file(self.checkpoint_path(*id))?;
+        }
+        Ok(excess)
+    }
+
+    /// Helper function to resolve the directory holding checkpoints and their file name prefix.
+    #[cfg(feature = "std")]
+    fn checkpoint_location(&self) -> (PathBuf, String) {
+        let stem = self
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let dir = match (&self.backup_dir, self.path.parent()) {
+            (Some(dir), _) => dir.clone(),
+            (None, Some(parent)) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        (dir, format!("{}.ckpt-", stem))
+    }
+
+    /// Helper function to resolve the file for a given checkpoint.
+    #[cfg(feature = "std")]
+    fn checkpoint_path(&self, id: CheckpointId) -> PathBuf {
+        let (dir, prefix) = self.checkpoint_location();
+        dir.join(format!("{}{}", prefix, id.0))
+    }
+
+    /// Helper function to resolve where the `.bak` file lives.
+    #[cfg(feature = "std")]
+    fn single_backup_path(&self) -> PathBuf {
+        match (&self.backup_dir, self.path.file_name()) {
+            (Some(dir), Some(name)) => {
+                let mut name = name.to_os_string();
+                name.push(".bak");
+                dir.join(name)
+            }
+            _ => self.path.with_extension("bak"),
+        }
+    }
+
+    /// Deletes timestamped backups (see `BackupStrategy::Timestamped`) taken more than
+    /// `max_age_secs` ago. Returns the number of backups removed.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn prune_backups(&self, max_age_secs: u64) -> Result<usize, TinyKVError> {
+        self.ensure_writable()?;
+        let now = Self::current_timestamp()?;
+        let mut removed = 0;
+        for (taken_at, path) in self.timestamped_backups()? {
+            if now.saturating_sub(taken_at) > max_age_secs {
+                fs::remove_file(path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Helper function to resolve the directory holding timestamped backups and the file
+    /// name parts around the timestamp: `<stem>.` and `.<ext>.bak` (or `.bak`).
+    #[cfg(feature = "std")]
+    fn timestamped_backup_location(&self) -> (PathBuf, String, String) {
+        let (dir, _) = self.checkpoint_location();
+        let stem = self
+            .path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let suffix = match self.path.extension() {
+            Some(ext) => format!(".{}.bak", ext.to_string_lossy()),
+            None => ".bak".to_string(),
+        };
+        (dir, format!("{}.", stem), suffix)
+    }
+
+    /// Helper function to get the path of the backup taken at `timestamp`, such as
+    /// `mydata.2024-01-02T15-04-05.json.bak` (UTC).
+    #[cfg(feature = "std")]
+    fn timestamped_backup_path(&self, timestamp: u64) -> PathBuf {
+        let (dir, prefix, suffix) = self.timestamped_backup_location();
+        dir.join(format!("{}{}{}", prefix, format_backup_time(timestamp), suffix))
+    }
+
+    /// Helper function to list timestamped backups with the time each was taken,
+    /// oldest first.
+    #[cfg(feature = "std")]
+    fn timestamped_backups(&self) -> Result<Vec<(u64, PathBuf)>, TinyKVError> {
+        let (dir, prefix, suffix) = self.timestamped_backup_location();
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut backups = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            if let Some(taken_at) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|rest| rest.strip_suffix(&suffix))
+                .and_then(parse_backup_time)
+            {
+                backups.push((taken_at, entry.path()));
+            }
+        }
+        backups.sort();
+        Ok(backups)
+    }
+
+    /// Helper function to get the path of the `n`th rotated backup (`.bak.<n>`).
+    #[cfg(feature = "std")]
+    fn rotated_backup_path(&self, n: usize) -> PathBuf {
+        let mut name = self.single_backup_path().into_os_string();
+        name.push(format!(".{n}"));
+        PathBuf::from(name)
+    }
+
+    /// Helper function to shift rotated backups down one slot, dropping the oldest,
+    /// so the next backup can be written to `.bak.1`.
+    #[cfg(feature = "std")]
+    fn rotate_backups(&self, count: usize) -> io::Result<()> {
+        for n in (1..count).rev() {
+            let from = self.rotated_backup_path(n);
+            if from.exists() {
+                Self::replace_file(&from, &self.rotated_backup_path(n + 1))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the freshly written temp file over the store file.
+    /// On Windows, renaming over an existing file can fail transiently while another
+    /// process (indexer, antivirus) holds a handle to it, so the rename is retried
+    /// before falling back to remove-then-rename.
+    #[cfg(feature = "std")]
+    fn replace_file(from: &Path, to: &Path) -> io::Result<()> {
+        #[cfg(windows)]
+        {
+            let mut last_err = None;
+            for _ in 0..5 {
+                match fs::rename(from, to) {
+                    Ok(()) => return Ok(()),
+                    Err(e)
+                        if matches!(
+                            e.kind(),
+                            ErrorKind::PermissionDenied | ErrorKind::AlreadyExists
+                        ) =>
+                    {
+                        last_err = Some(e);
+                        std::thread::sleep(std::time::Duration::from_millis(10));
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+
+            match fs::remove_file(to) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(_) => return Err(last_err.unwrap_or_else(|| io::Error::from(ErrorKind::Other))),
+            }
+            fs::rename(from, to)
+        }
+
+        #[cfg(not(windows))]
+        fs::rename(from, to)
+    }
+
+    /// Reports how many entries are expired and roughly how many bytes a
+    /// `purge_expired` followed by a save would reclaim. Read-only.
+    #[cfg(any(feature = "std", feature = "wasm"))]
+    pub fn gc_stats(&self) -> GcStats {
+        let now = Self::current_timestamp().unwrap_or(0);
+        let mut stats = GcStats::default();
+
+        for (key, entry) in self.data.iter().filter(|(key, _)| !Self::is_reserved_key(key)) {
+            stats.total_entries += 1;
+            if entry.expires_at.is_some_and(|expiry| now > expiry) {
+                stats.expired_entries += 1;
+                stats.reclaimable_bytes += Self::estimated_entry_size(key, entry);
+            }
+        }
+
+        stats
+    }
+
+    /// Helper function to estimate the serialized size of a single entry, including its key.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    fn estimated_entry_size(key: &str, entry: &Entry) -> usize {
+        // Quoted key, colon and separating comma around the serialized entry.
+        key.len() + 4 + serde_json::to_string_pretty(entry).map_or(0, |s| s.len())
+    }
+
+    #[cfg(all(feature = "nanoserde", any(feature = "std", feature = "wasm")))]
+    fn estimated_entry_size(key: &str, entry: &Entry) -> usize {
+        key.len() + 4 + entry.serialize_json().len()
+    }
+
+    /// Removes all expired entries from memory.
+    /// TTL checking only available with `std` feature.
+    pub fn purge_expired(&mut self) -> Result<usize, TinyKVError> {
+        self.purge_expired_into().map(|keys| keys.len())
+    }
+
+    /// Like `purge_expired`, but returns the removed keys, sorted and with the namespace
+    /// prefix stripped, e.g. to fan out cache invalidations. Auto-saves once if anything
+    /// was removed.
+    pub fn purge_expired_into(&mut self) -> Result<Vec<String>, TinyKVError> {
+        #[cfg(feature = "std")]
+        self.ensure_writable()?;
+        if self.data.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        {
+            let now = Self::current_timestamp()?;
+            let mut expired: Vec<String> = self
+                .data
+                .iter()
+                .filter(|(_, entry)| entry.expires_at.is_some_and(|expiry| now > expiry))
+                .map(|(key, _)| key.clone())
+                .collect();
+            for key in &expired {
+                self.remove_entry(key);
+            }
+            if !expired.is_empty() {
+                self.invalidate_read_cache();
+            }
+            expired.sort();
+
+            #[cfg(feature = "std")]
+            self.persist_removals(&expired)?;
+            #[cfg(feature = "wasm")]
+            if !expired.is_empty() && self.auto_save {
+                self.web_save()?;
+            }
+
+            Ok(expired.iter().map(|key| self.strip_namespace(key)).collect())
+        }
+
+        #[cfg(all(not(feature = "std"), not(feature = "wasm")))]
+        Ok(Vec::new()) // No TTL support in no_std
+    }
+
+    /// Clears all entries from memory. The schema version stamped by `with_migration`
+    /// is kept.
+    pub fn clear(&mut self) -> Result<(), TinyKVError> {
+        #[cfg(feature = "std")]
+        self.ensure_writable()?;
+        #[cfg(feature = "std")]
+        let cleared: Vec<String> = self
+            .data
+            .keys()
+            .filter(|key| !Self::is_reserved_key(key))
+            .cloned()
+            .collect();
+        self.data.retain(|key, _| Self::is_reserved_key(key));
+        self.invalidate_read_cache();
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        {
+            self.may_have_ttl = false;
+        }
+        #[cfg(feature = "std")]
+        {
+            self.ttl_entries = 0;
+        }
+        #[cfg(feature = "std")]
+        self.notify_change("", ChangeKind::Cleared);
+
+        #[cfg(feature = "std")]
+        if self.appends_records() {
+            self.append_tombstones(&cleared)?;
+        } else if self.auto_save {
+            self.save()?;
+        }
+        #[cfg(feature = "wasm")]
+        if self.auto_save {
+            self.web_save()?;
+        }
+
+        Ok(())
+    }
+
+    /// Clears all entries from memory without writing to disk, even with auto-save
+    /// enabled. The next save persists the empty (or repopulated) store. The schema
+    /// version stamped by `with_migration` is kept.
+    pub fn clear_memory(&mut self) {
+        self.data.retain(|key, _| Self::is_reserved_key(key));
+        self.invalidate_read_cache();
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        self.refresh_ttl_flag();
+    }
+
+    /// Replaces all entries with the ones in `data` (as produced by `to_data`) and,
+    /// for file-backed stores, saves once. The store is left untouched if `data`
+    /// fails to parse. The current schema version stamp is kept.
+    pub fn reset_to_data(&mut self, data: &str) -> Result<(), TinyKVError> {
+        #[cfg(feature = "std")]
+        self.ensure_writable()?;
+        let mut data = self.decode_data(data)?;
+        data.remove(VERSION_KEY);
+        if let Some(version) = self.data.remove(VERSION_KEY) {
+            data.insert(VERSION_KEY.to_string(), version);
+        }
+        self.data = data;
+        self.invalidate_read_cache();
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        self.refresh_ttl_flag();
+
+        #[cfg(feature = "std")]
+        if self.is_file_backed() {
+            self.save()?;
+        }
+        #[cfg(feature = "wasm")]
+        if self.auto_save {
+            self.web_save()?;
+        }
+
+        Ok(())
+    }
+
+    /// Captures the current entries (across all namespaces) in memory, for a later
+    /// `restore`. Nothing is written to disk.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            data: self.data.clone(),
+        }
+    }
+
+    /// Replaces all entries with the ones captured by `snapshot`, auto-saving if enabled.
+    /// The current schema version stamp is kept.
+    pub fn restore(&mut self, snap: Snapshot) -> Result<(), TinyKVError> {
+        #[cfg(feature = "std")]
+        self.ensure_writable()?;
+        self.load_snapshot(snap);
+
+        if self.auto_save {
+            #[cfg(feature = "std")]
+            self.save()?;
+            #[cfg(feature = "wasm")]
+            self.web_save()?;
+        }
+        Ok(())
+    }
+
+    /// Helper function to swap in a snapshot's entries without saving.
+    fn load_snapshot(&mut self, snap: Snapshot) {
+        let version = self.data.remove(VERSION_KEY);
+        self.data = snap.data;
+        if let Some(version) = version {
+            self.data.insert(VERSION_KEY.to_string(), version);
+        }
+        self.invalidate_read_cache();
+        #[cfg(any(feature = "std", feature = "wasm"))]
+        self.refresh_ttl_flag();
+    }
+
+    /// Runs `f` against the store as one unit: if it returns `Err`, every change it made is
+    /// rolled back before the error is returned; if it returns `Ok`, the changes are kept and
+    /// written in a single save (when auto-save is enabled, or for append-log stores).
+    /// Auto-saves and log appends are suppressed while `f` runs, so calling `save` inside
+    /// `f` is the only way it can reach disk before commit.
+    pub fn transaction<F, R>(&mut self, f: F) -> Result<R, TinyKVError>
+    where
+        F: FnOnce(&mut TinyKV) -> Result<R, TinyKVError>,
+    {
+        #[cfg(feature = "std")]
+        self.ensure_writable()?;
+        let snap = self.snapshot();
+
+        let guard = SuppressWrites::new(self);
+        let result = f(guard.kv);
+        drop(guard);
+
+        match result {
+            Ok(value) => {
+                #[cfg(feature = "std")]
+                if self.auto_save || self.append_log {
+                    self.save()?;
+                }
+                #[cfg(feature = "wasm")]
+                if self.auto_save {
+                    self.web_save()?;
+                }
+                Ok(value)
+            }
+            Err(e) => {
+                self.load_snapshot(snap);
+                Err(e)
+            }
+        }
+    }
+
+    /// Removes all entries that start with the given prefix, except the schema version
+    /// stamped by `with_migration`.
+    pub fn clear_prefix(&mut self, prefix: &str) -> Result<usize, TinyKVError> {
+        #[cfg(feature = "std")]
+        self.ensure_writable()?;
+        let before_count = self.data.len();
+        #[cfg(feature = "std")]
+        let mut removed = Vec::new();
+
+        self.data.retain(|key, _| {
+            let keep = !key.starts_with(prefix) || Self::is_reserved_key(key);
+            #[cfg(feature = "std")]
+            if !keep {
+                removed.push(key.clone());
+            }
+            keep
+        });
         
         let removed_count = before_count - self.data.len();
+        if removed_count > 0 {
+            self.invalidate_read_cache();
+            #[cfg(any(feature = "std", feature = "wasm"))]
+            self.refresh_ttl_flag();
+        }
+
+        #[cfg(feature = "std")]
+        self.persist_removals(&removed)?;
+        #[cfg(feature = "wasm")]
+        if removed_count > 0 && self.auto_save {
+            self.web_save()?;
+        }
+
+        Ok(removed_count)
+    }
+
+    /// Removes every entry whose key in the current namespace starts with `prefix` and
+    /// returns the unexpired ones as (key, value) pairs in key order, saving once.
+    /// Nothing is removed if any unexpired value fails to deserialize as `T`.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    pub fn remove_prefix_returning<T: for<'de> Deserialize<'de>>(
+        &mut self,
+        prefix: &str,
+    ) -> Result<Vec<(String, T)>, TinyKVError> {
+        self.ensure_writable()?;
+        let now = Self::current_timestamp()?;
+        let prefix = self.namespaced_key(prefix);
+
+        let mut keys: Vec<String> = self
+            .data
+            .keys()
+            .filter(|key| key.starts_with(&prefix) && !Self::is_reserved_key(key))
+            .cloned()
+            .collect();
+        if keys.is_empty() {
+            return Ok(Vec::new());
+        }
+        keys.sort();
+
+        let mut removed = Vec::new();
+        for key in &keys {
+            let entry = &self.data[key];
+            if entry.expires_at.is_none_or(|expiry| now <= expiry) {
+                let value = T::deserialize(entry.decoded_value()?.as_ref())?;
+                removed.push((self.strip_namespace(key), value));
+            }
+        }
+
+        for key in &keys {
+            self.remove_entry(key);
+        }
+        self.invalidate_read_cache();
+        self.persist_removals(&keys)?;
+        Ok(removed)
+    }
+
+    /// Rewrites the leading `from` of every unexpired key in the current namespace to `to`,
+    /// keeping each entry's value and expiry, and auto-saves once. A renamed key overwrites
+    /// any existing entry of the same name. Returns the number of keys renamed, or
+    /// `TinyKVError::ReservedKey` (renaming nothing) if a new name would be reserved.
+    #[cfg(any(feature = "std", feature = "wasm"))]
+    pub fn rename_prefix(&mut self, from: &str, to: &str) -> Result<usize, TinyKVError> {
+        #[cfg(feature = "std")]
+        self.ensure_writable()?;
+        let now = Self::current_timestamp()?;
+        let from = self.namespaced_key(from);
+        let to = self.namespaced_key(to);
+
+        let keys: Vec<String> = self
+            .data
+            .iter()
+            .filter(|(key, entry)| {
+                key.starts_with(&from)
+                    && !Self::is_reserved_key(key)
+                    && entry.expires_at.is_none_or(|expiry| now <= expiry)
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+        let renamed_to = |key: &str| format!("{}{}", to, &key[from.len()..]);
+        if let Some(target) = keys.iter().map(|key| renamed_to(key)).find(|target| {
+            Self::is_reserved_key(target)
+        }) {
+            return Err(TinyKVError::ReservedKey(self.strip_namespace(&target)));
+        }
+
+        // Take every entry out before reinserting, so a rename can't clobber a key
+        // that is itself about to be renamed.
+        let mut moved = Vec::with_capacity(keys.len());
+        for key in &keys {
+            if let Some(entry) = self.remove_entry(key) {
+                moved.push((renamed_to(key), entry));
+            }
+        }
+
+        let renamed = moved.len();
+        #[cfg(feature = "std")]
+        let targets: Vec<String> = moved.iter().map(|(key, _)| key.clone()).collect();
+        for (key, entry) in moved {
+            self.put_entry(key, entry);
+        }
+        self.invalidate_read_cache();
+
+        if renamed > 0 {
+            #[cfg(feature = "std")]
+            self.persist_moves(&keys, &targets)?;
+            #[cfg(feature = "wasm")]
+            if self.auto_save {
+                self.web_save()?;
+            }
+        }
+
+        Ok(renamed)
+    }
+
+    /// Moves the unexpired entry at `from` to `to` in the current namespace, keeping its
+    /// value and expiry, and auto-saves once. An existing entry at `to` is overwritten.
+    /// Returns `false` if `from` is absent or expired; renaming a live key to itself
+    /// changes nothing and returns `true`. Reserved keys count as absent for `from` and
+    /// fail with `TinyKVError::ReservedKey` for `to`.
+    #[cfg(any(feature = "std", feature = "wasm"))]
+    pub fn rename_key(&mut self, from: &str, to: &str) -> Result<bool, TinyKVError> {
+        #[cfg(feature = "std")]
+        self.ensure_writable()?;
+        self.check_user_key(to)?;
+        let now = Self::current_timestamp()?;
+        let from = self.namespaced_key(from);
+        let to = self.namespaced_key(to);
+
+        let live = !Self::is_reserved_key(&from)
+            && self
+                .data
+                .get(&from)
+                .is_some_and(|entry| entry.expires_at.is_none_or(|expiry| now <= expiry));
+        if !live || from == to {
+            return Ok(live);
+        }
+
+        if let Some(entry) = self.remove_entry(&from) {
+            self.put_entry(to.clone(), entry);
+        }
+
+        #[cfg(feature = "std")]
+        self.persist_moves(&[from], &[to])?;
+        #[cfg(feature = "wasm")]
+        if self.auto_save {
+            self.web_save()?;
+        }
+        Ok(true)
+    }
+
+    /// Reloads the store contents from disk.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn reload(&mut self) -> Result<(), TinyKVError> {
+        let started = Instant::now();
+        #[cfg(feature = "file-lock")]
+        let lock = self.lock_for_io()?;
+        self.data = self.read_file_data()?;
+        #[cfg(feature = "file-lock")]
+        drop(lock);
+        self.invalidate_read_cache();
+        self.purge_loaded()?;
+        self.refresh_ttl_flag();
+        *self
+            .last_saved_hash
+            .get_mut()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+        self.observe(Op::Load, started);
+        Ok(())
+    }
+
+    /// Helper function to render the store file contents in its current format.
+    #[cfg(feature = "std")]
+    fn file_contents(&self) -> Result<String, TinyKVError> {
+        let started = Instant::now();
+        let json = if self.append_log {
+            self.serialize_log()?
+        } else {
+            self.serialize_data()?
+        };
+        self.observe(Op::Serialize, started);
+
+        Ok(match &self.pre_save {
+            Some(hook) if !self.append_log => hook(json),
+            _ => json,
+        })
+    }
+
+    /// Helper function to read and parse the store file in its current format.
+    #[cfg(feature = "std")]
+    fn read_file_data(&self) -> Result<HashMap<String, Entry>, TinyKVError> {
+        match fs::read(&self.path) {
+            Ok(bytes) => self.parse_file_bytes(bytes),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(TinyKVError::Io(e)),
+        }
+    }
+
+    /// Helper function to decrypt, decompress and deserialize the raw bytes of the store file.
+    #[cfg(feature = "std")]
+    fn parse_file_bytes(&self, bytes: Vec<u8>) -> Result<HashMap<String, Entry>, TinyKVError> {
+        #[cfg(feature = "encryption")]
+        let bytes = match &self.encryption_key {
+            Some(key) => Self::decrypt(key, &bytes)?,
+            None => bytes,
+        };
+        let contents = Self::decode_file_bytes(bytes)?;
+
+        let started = Instant::now();
+        let data = match &self.post_load {
+            _ if self.append_log => Self::deserialize_log(&contents)?,
+            Some(hook) => self.decode_data(&hook(contents))?,
+            None => self.decode_data(&contents)?,
+        };
+        self.observe(Op::Deserialize, started);
+        Ok(data)
+    }
+
+    /// Helper function to read the store file as text, transparently
+    /// decompressing it if it starts with the gzip magic bytes (`compression` feature).
+    #[cfg(feature = "std")]
+    fn read_file_text(path: &Path) -> io::Result<String> {
+        Self::decode_file_bytes(fs::read(path)?)
+    }
+
+    /// Helper function to turn raw file bytes into text, gunzipping them if needed.
+    /// Without the `compression` feature a gzipped file fails with a clear error
+    /// instead of an opaque UTF-8 one.
+    #[cfg(feature = "std")]
+    fn decode_file_bytes(bytes: Vec<u8>) -> io::Result<String> {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            #[cfg(feature = "compression")]
+            {
+                let mut contents = String::new();
+                flate2::read::GzDecoder::new(bytes.as_slice()).read_to_string(&mut contents)?;
+                return Ok(contents);
+            }
+            #[cfg(not(feature = "compression"))]
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "store file is gzip-compressed but tinykv was compiled without the \
+                 `compression` feature",
+            ));
+        }
+        String::from_utf8(bytes).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+    }
+
+    /// Helper function to turn the serialized store into the bytes written to disk,
+    /// applying compression and encryption if configured.
+    #[cfg(feature = "std")]
+    fn encode_file(&self, json: String) -> Result<Vec<u8>, TinyKVError> {
+        #[cfg(feature = "compression")]
+        let bytes = if self.compress_file && !self.append_log {
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(json.as_bytes())?;
+            encoder.finish()?
+        } else {
+            json.into_bytes()
+        };
+        #[cfg(not(feature = "compression"))]
+        let bytes = json.into_bytes();
+
+        #[cfg(feature = "encryption")]
+        if let Some(key) = &self.encryption_key {
+            return Self::encrypt(key, &bytes);
+        }
+        Ok(bytes)
+    }
+
+    /// Helper function to encrypt file bytes, prepending the random nonce.
+    #[cfg(feature = "encryption")]
+    fn encrypt(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, TinyKVError> {
+        use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+        use chacha20poly1305::ChaCha20Poly1305;
+
+        let cipher = ChaCha20Poly1305::new(key.into());
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| TinyKVError::Serialization("encryption failed".to_string()))?;
+
+        let mut bytes = nonce.to_vec();
+        bytes.extend(ciphertext);
+        Ok(bytes)
+    }
+
+    /// Helper function to decrypt file bytes written by `encrypt`.
+    #[cfg(feature = "encryption")]
+    fn decrypt(key: &[u8; 32], bytes: &[u8]) -> Result<Vec<u8>, TinyKVError> {
+        use chacha20poly1305::aead::{Aead, KeyInit};
+        use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+
+        const NONCE_LEN: usize = 12;
+        if bytes.len() < NONCE_LEN {
+            return Err(TinyKVError::Decryption);
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        ChaCha20Poly1305::new(key.into())
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| TinyKVError::Decryption)
+    }
+
+    /// Helper function to serialize the store as one record line per entry.
+    #[cfg(feature = "std")]
+    fn serialize_log(&self) -> Result<String, TinyKVError> {
+        let mut log = String::new();
+        for (key, entry) in &self.data {
+            log.push_str(&Self::serialize_record(key, entry)?);
+            log.push('\n');
+        }
+        Ok(log)
+    }
+
+    /// Helper function to replay a log, letting later records override earlier ones.
+    #[cfg(feature = "std")]
+    fn deserialize_log(contents: &str) -> Result<HashMap<String, Entry>, TinyKVError> {
+        let mut data = HashMap::new();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            match Self::parse_tombstone(line) {
+                Some(key) => {
+                    data.remove(&key);
+                }
+                None => data.extend(Self::deserialize_data(line)?),
+            }
+        }
+        Ok(data)
+    }
+
+    /// Helper function to serialize a tombstone line recording that `key` was removed.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    fn serialize_tombstone(key: &str) -> Result<String, TinyKVError> {
+        serde_json::to_string(&Tombstone {
+            key: key.to_string(),
+            deleted: true,
+        })
+        .map_err(Into::into)
+    }
 
-        if removed_count > 0 && self.auto_save {
-            #[cfg(feature = "std")]
-            self.save()?;
-            #[cfg(feature = "wasm")]
-            self.web_save()?;
+    #[cfg(all(feature = "nanoserde", feature = "std"))]
+    fn serialize_tombstone(key: &str) -> Result<String, TinyKVError> {
+        Ok(Tombstone {
+            key: key.to_string(),
+            deleted: true,
         }
+        .serialize_json())
+    }
 
-        Ok(removed_count)
+    /// Helper function to return the removed key if a log line is a tombstone.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    fn parse_tombstone(line: &str) -> Option<String> {
+        serde_json::from_str::<Tombstone>(line)
+            .ok()
+            .filter(|tombstone| tombstone.deleted)
+            .map(|tombstone| tombstone.key)
     }
 
-    /// Reloads the store contents from disk.
+    #[cfg(all(feature = "nanoserde", feature = "std"))]
+    fn parse_tombstone(line: &str) -> Option<String> {
+        Tombstone::deserialize_json(line)
+            .ok()
+            .filter(|tombstone| tombstone.deleted)
+            .map(|tombstone| tombstone.key)
+    }
+
+    /// Helper function to serialize a single entry as a one-key JSON object.
+    #[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+    fn serialize_record(key: &str, entry: &Entry) -> Result<String, TinyKVError> {
+        serde_json::to_string(&HashMap::from([(key, entry)])).map_err(Into::into)
+    }
+
+    #[cfg(all(feature = "nanoserde", feature = "std"))]
+    fn serialize_record(key: &str, entry: &Entry) -> Result<String, TinyKVError> {
+        Ok(format!(
+            "{{{}:{}}}",
+            key.to_string().serialize_json(),
+            entry.serialize_json()
+        ))
+    }
+
+    /// Returns a handle to `key` whose methods are typed to `T`, so repeated reads and
+    /// writes need neither the key nor a turbofish. The handle borrows the store mutably.
     /// Only available with `std` feature.
+    ///
+    /// ```
+    /// # fn main() -> Result<(), tinykv::TinyKVError> {
+    /// let mut kv = tinykv::TinyKV::new();
+    /// let mut visits = kv.entry::<u32>("visits");
+    /// let count = visits.get()?.unwrap_or(0);
+    /// visits.set(count + 1)?;
+    /// assert_eq!(visits.get()?, Some(1));
+    /// assert!(visits.exists());
+    /// # Ok(())
+    /// # }
+    /// ```
     #[cfg(feature = "std")]
-    pub fn reload(&mut self) -> Result<(), TinyKVError> {
-        let data = match fs::read_to_string(&self.path) {
-            Ok(contents) => Self::deserialize_data(&contents)?,
-            Err(e) if e.kind() == ErrorKind::NotFound => HashMap::new(),
-            Err(e) => return Err(TinyKVError::Io(e)),
+    pub fn entry<T>(&mut self, key: &str) -> TypedKey<'_, T> {
+        TypedKey {
+            kv: self,
+            key: key.to_string(),
+            value_type: PhantomData,
+        }
+    }
+
+    /// Acquires a lease-style lock stored under the key `__lock:<name>` in the current
+    /// namespace with the given TTL, so a crashed holder's lock expires on its own. Returns
+    /// `None` if another holder's lock is still live. For file-backed stores the lock state
+    /// is re-read from disk first and persisted immediately, so processes sharing the file
+    /// observe each other's locks. With the `file-lock` feature the read, check and write
+    /// happen under the store's file lock, so only one process can acquire a given lock;
+    /// without it, two processes racing for the same lock may both get it.
+    ///
+    /// The lock key holds a token unique to this acquisition. Dropping the returned guard
+    /// re-reads the key and removes it only if it still holds that token, so a guard whose
+    /// lock expired and was taken over leaves the new holder's lock in place. While held,
+    /// the lock is an ordinary entry: it shows up in `keys()`, `len()` and exports.
+    /// Only available with `std` feature.
+    #[cfg(feature = "std")]
+    pub fn acquire_lock(
+        &mut self,
+        name: &str,
+        ttl_secs: u64,
+    ) -> Result<Option<LockGuard<'_>>, TinyKVError> {
+        self.ensure_writable()?;
+        let key = format!("__lock:{}", name);
+        let token = Self::lock_token();
+
+        if !self.with_file_lock_held(|kv| kv.try_acquire_lock(&key, &token, ttl_secs))? {
+            return Ok(None);
+        }
+        Ok(Some(LockGuard {
+            kv: self,
+            key,
+            token,
+        }))
+    }
+
+    /// Helper function for `acquire_lock`: re-reads the lock key, and claims it if free.
+    #[cfg(feature = "std")]
+    fn try_acquire_lock(
+        &mut self,
+        key: &str,
+        token: &str,
+        ttl_secs: u64,
+    ) -> Result<bool, TinyKVError> {
+        self.refresh_from_disk(key)?;
+        if self.contains_key(key) {
+            return Ok(false);
+        }
+
+        // Lock writes must reach disk even when the auto-save was coalesced away; an
+        // auto-save that did happen leaves nothing changed to write.
+        self.set_with_ttl(key, token.to_string(), ttl_secs)?;
+        if self.is_file_backed() && !self.appends_records() {
+            self.save_if_changed()?;
+        }
+        Ok(true)
+    }
+
+    /// Helper function to release a lock key, if it still holds `token`, and persist the
+    /// release.
+    #[cfg(feature = "std")]
+    fn release_lock(&mut self, key: &str, token: &str) -> Result<(), TinyKVError> {
+        self.with_file_lock_held(|kv| {
+            kv.refresh_from_disk(key)?;
+            let owner: Option<String> = kv.get(key).ok().flatten();
+            if owner.as_deref() == Some(token)
+                && kv.remove(key)?
+                && kv.is_file_backed()
+                && !kv.auto_save
+            {
+                kv.save()?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Helper function to generate a lock token unique across processes and acquisitions.
+    #[cfg(feature = "std")]
+    fn lock_token() -> String {
+        static NEXT_TOKEN: AtomicU64 = AtomicU64::new(0);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos());
+        let count = NEXT_TOKEN.fetch_add(1, Ordering::Relaxed);
+        format!("{}-{}-{}", std::process::id(), nanos, count)
+    }
+
+    /// Helper function to run `f` under the store's file lock. The lock is parked in
+    /// `file_lock` so the nested reload and save don't try to take it a second time.
+    #[cfg(feature = "std")]
+    fn with_file_lock_held<R>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<R, TinyKVError>,
+    ) -> Result<R, TinyKVError> {
+        #[cfg(feature = "file-lock")]
+        let took_file_lock = match self.lock_for_io()? {
+            Some(lock) => {
+                self.file_lock = Some(lock);
+                true
+            }
+            None => false,
         };
+        let result = f(self);
+        #[cfg(feature = "file-lock")]
+        if took_file_lock {
+            self.file_lock = None;
+        }
+        result
+    }
 
-        self.data = data;
+    /// Returns a future that resolves once `key`'s expiry has passed. The future holds its
+    /// own clone of `kv` rather than a borrow, so it can be spawned while other tasks keep
+    /// using the store; the lock is only taken to read the expiry, never across a sleep or
+    /// file read. Before resolving it re-checks the expiry, including the one on disk for
+    /// file-backed stores (read on tokio's blocking pool), and keeps waiting if the key was
+    /// renewed meanwhile. Resolves immediately if the key is absent or has no TTL.
+    /// Only available with the `tokio` feature.
+    #[cfg(feature = "tokio")]
+    pub fn expiry_notification(
+        kv: std::sync::Arc<Mutex<TinyKV>>,
+        key: &str,
+    ) -> impl Future<Output = ()> + Send + 'static {
+        let namespaced_key = Self::lock_store(&kv).namespaced_key(key);
+
+        async move {
+            loop {
+                let (in_memory, path) = {
+                    let kv = Self::lock_store(&kv);
+                    let expiry = kv.data.get(&namespaced_key).and_then(|e| e.expires_at);
+                    (expiry, kv.is_file_backed().then(|| kv.path.clone()))
+                };
+                let on_disk = match path {
+                    Some(path) => tokio::task::spawn_blocking(move || fs::read(path))
+                        .await
+                        .ok()
+                        .and_then(Result::ok)
+                        .and_then(|bytes| Self::lock_store(&kv).parse_file_bytes(bytes).ok())
+                        .and_then(|data| data.get(&namespaced_key).and_then(|e| e.expires_at)),
+                    None => None,
+                };
+                let Some(expiry) = in_memory.max(on_disk) else {
+                    return;
+                };
+                let Ok(now) = Self::current_timestamp() else {
+                    return;
+                };
+                if now > expiry {
+                    return;
+                }
+                tokio::time::sleep(Duration::from_secs(expiry - now + 1)).await;
+            }
+        }
+    }
+
+    /// Helper function for `expiry_notification` to lock a shared store, recovering it
+    /// if another holder panicked.
+    #[cfg(feature = "tokio")]
+    fn lock_store(kv: &Mutex<TinyKV>) -> std::sync::MutexGuard<'_, TinyKV> {
+        kv.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Helper function to replace one in-memory entry with its current on-disk state.
+    #[cfg(feature = "std")]
+    fn refresh_from_disk(&mut self, key: &str) -> Result<(), TinyKVError> {
+        if !self.is_file_backed() {
+            return Ok(());
+        }
+        let mut on_disk = self.read_file_data()?;
+
+        let namespaced_key = self.namespaced_key(key);
+        match on_disk.remove(&namespaced_key) {
+            Some(entry) => {
+                self.may_have_ttl |= entry.expires_at.is_some();
+                self.put_entry(namespaced_key, entry);
+            }
+            None => {
+                self.remove_entry(&namespaced_key);
+            }
+        }
         Ok(())
     }
+
+    /// Helper function to tell file-backed stores apart from `TinyKV::new()` ones.
+    #[cfg(feature = "std")]
+    fn is_file_backed(&self) -> bool {
+        !self.path.as_os_str().is_empty()
+    }
+}
+
+/// Holds a lock acquired with `TinyKV::acquire_lock` and releases it on drop.
+/// Dereferences to the store so it stays usable while the lock is held.
+#[cfg(feature = "std")]
+pub struct LockGuard<'a> {
+    kv: &'a mut TinyKV,
+    key: String,
+    token: String,
+}
+
+#[cfg(feature = "std")]
+impl core::ops::Deref for LockGuard<'_> {
+    type Target = TinyKV;
+
+    fn deref(&self) -> &TinyKV {
+        self.kv
+    }
+}
+
+#[cfg(feature = "std")]
+impl core::ops::DerefMut for LockGuard<'_> {
+    fn deref_mut(&mut self) -> &mut TinyKV {
+        self.kv
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for LockGuard<'_> {
+    fn drop(&mut self) {
+        let _ = self.kv.release_lock(&self.key, &self.token);
+    }
+}
+
+/// A handle to a single key with a fixed value type, returned by `TinyKV::entry`.
+/// Borrows the store mutably for its lifetime.
+#[cfg(feature = "std")]
+pub struct TypedKey<'a, T> {
+    kv: &'a mut TinyKV,
+    key: String,
+    value_type: PhantomData<fn() -> T>,
+}
+
+#[cfg(feature = "std")]
+impl<T> TypedKey<'_, T> {
+    /// Deletes the key, like `TinyKV::remove`.
+    pub fn remove(&mut self) -> Result<bool, TinyKVError> {
+        self.kv.remove(&self.key)
+    }
+
+    /// Returns true if the key holds a live value, like `TinyKV::contains_key`.
+    pub fn exists(&self) -> bool {
+        self.kv.contains_key(&self.key)
+    }
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+impl<T: Serialize + for<'de> Deserialize<'de>> TypedKey<'_, T> {
+    /// Retrieves the value, like `TinyKV::get`.
+    pub fn get(&mut self) -> Result<Option<T>, TinyKVError> {
+        self.kv.get(&self.key)
+    }
+
+    /// Stores the value without expiration, like `TinyKV::set`.
+    pub fn set(&mut self, value: T) -> Result<(), TinyKVError> {
+        self.kv.set(&self.key, value)
+    }
+
+    /// Stores the value with a TTL in seconds, like `TinyKV::set_with_ttl`.
+    pub fn set_with_ttl(&mut self, value: T, ttl_secs: u64) -> Result<(), TinyKVError> {
+        self.kv.set_with_ttl(&self.key, value, ttl_secs)
+    }
+}
+
+#[cfg(all(feature = "nanoserde", feature = "std"))]
+impl<T: SerJson + DeJson> TypedKey<'_, T> {
+    /// Retrieves the value, like `TinyKV::get`.
+    pub fn get(&mut self) -> Result<Option<T>, TinyKVError> {
+        self.kv.get(&self.key)
+    }
+
+    /// Stores the value without expiration, like `TinyKV::set`.
+    pub fn set(&mut self, value: T) -> Result<(), TinyKVError> {
+        self.kv.set(&self.key, value)
+    }
+
+    /// Stores the value with a TTL in seconds, like `TinyKV::set_with_ttl`.
+    pub fn set_with_ttl(&mut self, value: T, ttl_secs: u64) -> Result<(), TinyKVError> {
+        self.kv.set_with_ttl(&self.key, value, ttl_secs)
+    }
+}
+
+/// A child namespace of a store, returned by `TinyKV::sub`. Each call runs against the
+/// store with the view's namespace in place of the store's own.
+#[cfg(feature = "std")]
+pub struct NamespacedView<'a> {
+    kv: &'a mut TinyKV,
+    namespace: String,
+}
+
+#[cfg(feature = "std")]
+impl NamespacedView<'_> {
+    /// Returns the full key prefix of this view, e.g. `app1:user:`.
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// Derives a further nested view, e.g. `app1:user:` + `prefs` gives `app1:user:prefs:`.
+    pub fn sub(&mut self, segment: &str) -> NamespacedView<'_> {
+        let namespace = format!("{}{}", self.namespace, TinyKV::namespace_prefix(segment));
+        NamespacedView {
+            kv: self.kv,
+            namespace,
+        }
+    }
+
+    /// Deletes a key in this view, like `TinyKV::remove`.
+    pub fn remove(&mut self, key: &str) -> Result<bool, TinyKVError> {
+        self.kv.in_namespace(&self.namespace, |kv| kv.remove(key))
+    }
+
+    /// Checks whether a live key exists in this view, like `TinyKV::contains_key`.
+    pub fn contains_key(&mut self, key: &str) -> bool {
+        self.kv.in_namespace(&self.namespace, |kv| kv.contains_key(key))
+    }
+
+    /// Lists the live keys in this view with its prefix stripped, like `TinyKV::keys`.
+    pub fn keys(&mut self) -> Vec<String> {
+        self.kv.in_namespace(&self.namespace, |kv| kv.keys())
+    }
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+impl NamespacedView<'_> {
+    /// Retrieves a key in this view, like `TinyKV::get`.
+    pub fn get<T: for<'de> Deserialize<'de>>(
+        &mut self,
+        key: &str,
+    ) -> Result<Option<T>, TinyKVError> {
+        self.kv.in_namespace(&self.namespace, |kv| kv.get(key))
+    }
+
+    /// Stores a key in this view, like `TinyKV::set`.
+    pub fn set<T: Serialize>(&mut self, key: &str, value: T) -> Result<(), TinyKVError> {
+        self.kv.in_namespace(&self.namespace, |kv| kv.set(key, value))
+    }
+
+    /// Stores a key in this view with a TTL in seconds, like `TinyKV::set_with_ttl`.
+    pub fn set_with_ttl<T: Serialize>(
+        &mut self,
+        key: &str,
+        value: T,
+        ttl_secs: u64,
+    ) -> Result<(), TinyKVError> {
+        self.kv.in_namespace(&self.namespace, |kv| kv.set_with_ttl(key, value, ttl_secs))
+    }
+}
+
+#[cfg(all(feature = "nanoserde", feature = "std"))]
+impl NamespacedView<'_> {
+    /// Retrieves a key in this view, like `TinyKV::get`.
+    pub fn get<T: DeJson>(&mut self, key: &str) -> Result<Option<T>, TinyKVError> {
+        self.kv.in_namespace(&self.namespace, |kv| kv.get(key))
+    }
+
+    /// Stores a key in this view, like `TinyKV::set`.
+    pub fn set<T: SerJson>(&mut self, key: &str, value: T) -> Result<(), TinyKVError> {
+        self.kv.in_namespace(&self.namespace, |kv| kv.set(key, value))
+    }
+
+    /// Stores a key in this view with a TTL in seconds, like `TinyKV::set_with_ttl`.
+    pub fn set_with_ttl<T: SerJson>(
+        &mut self,
+        key: &str,
+        value: T,
+        ttl_secs: u64,
+    ) -> Result<(), TinyKVError> {
+        self.kv.in_namespace(&self.namespace, |kv| kv.set_with_ttl(key, value, ttl_secs))
+    }
+}
+
+/// Removes a temp file on drop unless it was disarmed after a successful rename,
+/// so failed saves don't leave orphaned `.tmp` files behind.
+#[cfg(feature = "std")]
+struct TempFileGuard<'a> {
+    path: &'a Path,
+    armed: bool,
+}
+
+#[cfg(feature = "std")]
+impl<'a> TempFileGuard<'a> {
+    fn new(path: &'a Path) -> Self {
+        Self { path, armed: true }
+    }
+
+    fn disarm(&mut self) {
+        self.armed = false;
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for TempFileGuard<'_> {
+    fn drop(&mut self) {
+        if self.armed {
+            let _ = fs::remove_file(self.path);
+        }
+    }
+}
+
+/// Turns off auto-saves and log appends while `TinyKV::transaction` runs its closure,
+/// restoring them on drop so a panicking closure doesn't leave them off.
+struct SuppressWrites<'a> {
+    kv: &'a mut TinyKV,
+    auto_save: bool,
+}
+
+impl<'a> SuppressWrites<'a> {
+    fn new(kv: &'a mut TinyKV) -> Self {
+        let auto_save = core::mem::replace(&mut kv.auto_save, false);
+        #[cfg(feature = "std")]
+        {
+            kv.suppress_writes = true;
+        }
+        Self { kv, auto_save }
+    }
+}
+
+impl Drop for SuppressWrites<'_> {
+    fn drop(&mut self) {
+        self.kv.auto_save = self.auto_save;
+        #[cfg(feature = "std")]
+        {
+            self.kv.suppress_writes = false;
+        }
+    }
+}
+
+/// Operation reported to the callback set with `TinyKV::with_observer`.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Op {
+    /// Writing the store file with `save`
+    Save,
+    /// Reloading the store file with `reload` (and the helpers built on it)
+    Load,
+    /// Rendering the store as JSON text
+    Serialize,
+    /// Parsing the store file's JSON text
+    Deserialize,
+    /// A successful `get`
+    Get,
+    /// A successful `set` or `set_with_ttl`
+    Set,
+    /// Flushing a saved file and its directory to stable storage (see `with_fsync_interval`)
+    Fsync,
+}
+
+/// Kind of mutation reported to callbacks registered with `TinyKV::on_change`.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    /// The key was written
+    Set,
+    /// The key was removed with `remove`
+    Removed,
+    /// The key was dropped by a read after its TTL passed
+    Expired,
+    /// The whole store was cleared
+    Cleared,
+}
+
+/// Which entry to drop when a write overflows the capacity set by `TinyKV::with_capacity`.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Least recently read (or written, if never read)
+    #[default]
+    Lru,
+    /// Fewest reads, ties broken by the oldest access
+    Lfu,
+    /// Oldest created
+    Fifo,
+}
+
+/// How `TinyKV::merge_file` resolves a key present in both stores.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Keep this store's entry
+    KeepExisting,
+    /// Take the other file's entry
+    Overwrite,
+    /// Take whichever entry was written last, keeping this store's entry on a tie
+    #[default]
+    Newest,
+}
+
+/// How `save` names and keeps backups once enabled with `TinyKV::with_backup`.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackupStrategy {
+    /// A single `.bak` file, replaced on every save
+    #[default]
+    Overwrite,
+    /// The last N backups as `.bak.1` (newest) through `.bak.N`
+    Rotating(usize),
+    /// One backup per save named with its UTC time, e.g. `mydata.2024-01-02T15-04-05.json.bak`;
+    /// saves within the same second share a file. Remove old ones with `TinyKV::prune_backups`.
+    Timestamped,
+}
+
+/// Formats a UNIX timestamp as `YYYY-MM-DDTHH-MM-SS` (UTC) for backup file names.
+#[cfg(feature = "std")]
+fn format_backup_time(timestamp: u64) -> String {
+    // Civil-from-days conversion (proleptic Gregorian calendar), see
+    // http://howardhinnant.github.io/date_algorithms.html
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let secs = timestamp % 86_400;
+    let era = days.div_euclid(146_097);
+    let doe = days.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}-{:02}-{:02}",
+        year,
+        month,
+        day,
+        secs / 3_600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Parses a timestamp written by `format_backup_time`, or `None` if `text` isn't one.
+#[cfg(feature = "std")]
+fn parse_backup_time(text: &str) -> Option<u64> {
+    let field = |range: core::ops::Range<usize>| text.get(range)?.parse::<i64>().ok();
+    if text.len() != 19 {
+        return None;
+    }
+    let (year, month, day) = (field(0..4)?, field(5..7)?, field(8..10)?);
+    let (hour, minute, second) = (field(11..13)?, field(14..16)?, field(17..19)?);
+
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+    let timestamp = u64::try_from(days * 86_400 + hour * 3_600 + minute * 60 + second).ok()?;
+
+    // Reject out-of-range fields and stray separators by requiring an exact round trip.
+    (format_backup_time(timestamp) == text).then_some(timestamp)
+}
+
+/// Identifies a checkpoint written by `TinyKV::checkpoint`: the millisecond
+/// UNIX timestamp it was taken at.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CheckpointId(pub u64);
+
+/// In-memory copy of a store's entries taken by `TinyKV::snapshot`.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    #[cfg(any(feature = "std", feature = "wasm"))]
+    data: HashMap<String, Entry>,
+    #[cfg(all(not(feature = "std"), not(feature = "wasm")))]
+    data: BTreeMap<String, Entry>,
+}
+
+/// Remaining lifetime of a live key, returned by `TinyKV::get_ttl`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ttl {
+    /// The key never expires
+    None,
+    /// The key expires in this many seconds
+    Expires(u64),
+}
+
+/// Snapshot of reclaimable space returned by `TinyKV::gc_stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GcStats {
+    /// Number of entries held in memory, expired or not
+    pub total_entries: usize,
+    /// Number of entries whose TTL has passed
+    pub expired_entries: usize,
+    /// Estimated bytes the expired entries occupy in the serialized store
+    pub reclaimable_bytes: usize,
+}
+
+/// Append-log line recording that a key was removed, e.g. `{"key":"a","deleted":true}`.
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Tombstone {
+    key: String,
+    deleted: bool,
+}
+
+#[cfg(all(feature = "nanoserde", feature = "std"))]
+#[derive(DeJson, SerJson)]
+struct Tombstone {
+    key: String,
+    deleted: bool,
+}
+
+/// Object map that rejects duplicate keys, used by the strict loaders.
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+struct StrictEntries(HashMap<String, Entry>);
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+impl<'de> Deserialize<'de> for StrictEntries {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct StrictVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for StrictVisitor {
+            type Value = StrictEntries;
+
+            fn expecting(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                f.write_str("a TinyKV object map")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut data = HashMap::new();
+                while let Some((key, entry)) = map.next_entry::<String, Entry>()? {
+                    if data.contains_key(&key) {
+                        return Err(serde::de::Error::custom(format!("duplicate key `{}`", key)));
+                    }
+                    data.insert(key, entry);
+                }
+                Ok(StrictEntries(data))
+            }
+        }
+
+        deserializer.deserialize_map(StrictVisitor)
+    }
 }
 
 impl Default for TinyKV {
@@ -841,6 +5081,7 @@ impl Default for TinyKV {
 
 impl Drop for TinyKV {
     fn drop(&mut self) {
+        // Also flushes writes held back by the coalesce window.
         if self.auto_save {
             #[cfg(feature = "std")]
             let _ = self.save();
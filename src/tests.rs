@@ -98,6 +98,133 @@ fn test_serialization() {
     }
 }
 
+#[cfg(all(not(feature = "nanoserde"), not(feature = "std"), not(feature = "wasm")))]
+#[test]
+fn test_no_std_round_trip() {
+    let mut kv = TinyKV::new();
+    kv.set("plain", "alice").unwrap();
+    kv.set("quote\"key", "line\nbreak \\ \"quoted\" \u{1} ünï").unwrap();
+    let data = kv.to_data().unwrap();
+
+    let restored = TinyKV::from_data(&data).unwrap();
+    assert_eq!(restored.get("plain"), Some("alice".into()));
+    assert_eq!(
+        restored.get("quote\"key"),
+        Some("line\nbreak \\ \"quoted\" \u{1} ünï".into())
+    );
+    assert_eq!(restored.to_data().unwrap(), data);
+
+    let kv = TinyKV::from_data(
+        r#" { "a" : { "expires_at" : 99 , "value" : "\u00e9\ud83d\ude00\/" } } "#,
+    )
+    .unwrap();
+    assert_eq!(kv.get("a"), Some("é😀/".into()));
+    assert!(kv.to_data().unwrap().contains(r#""expires_at":99"#));
+
+    assert!(TinyKV::from_data(r#"{"a":{"value":"x"}"#).is_err());
+    assert!(TinyKV::from_data(r#"{"a":{"value":1}}"#).is_err());
+    assert!(TinyKV::from_data(r#"{"a":{"value":"x","extra":null}}"#).is_err());
+    assert!(TinyKV::from_data(r#"{"a":{"expires_at":null}}"#).is_err());
+}
+
+#[cfg(all(not(feature = "nanoserde"), not(feature = "std")))]
+#[test]
+fn test_no_std_escapes_keys_and_values() {
+    let mut kv = TinyKV::new();
+    kv.set("say\\hi", "he said \"hi\"\n\tbye\u{1f}").unwrap();
+    assert_eq!(
+        kv.to_data().unwrap(),
+        r#"{"say\\hi":{"value":"he said \"hi\"\n\tbye\u001f","expires_at":null}}"#
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_push_namespace() {
+    let mut kv = TinyKV::new().with_namespace("a").push_namespace("b");
+    kv.set("key", "value".to_string()).unwrap();
+    assert_eq!(kv.keys(), vec!["key".to_string()]);
+
+    let json = kv.to_data().unwrap();
+    assert!(json.contains("a:b:key"));
+}
+
+#[cfg(any(feature = "std", feature = "nanoserde"))]
+#[test]
+fn test_from_bytes() {
+    let mut source = TinyKV::new();
+    source.set("device_id", "ESP32_001".to_string()).unwrap();
+    let bytes = source.to_data().unwrap().into_bytes();
+
+    let mut kv = TinyKV::from_bytes(&bytes).unwrap();
+    let device_id: String = kv.get("device_id").unwrap().unwrap();
+    assert_eq!(device_id, "ESP32_001");
+
+    assert!(TinyKV::from_bytes(&[0xff, 0xfe]).is_err());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_keys_ref() {
+    let mut kv = TinyKV::new().with_namespace("app");
+    kv.set("a", 1).unwrap();
+    kv.set("b", 2).unwrap();
+    kv.set_with_ttl("stale", 3, 0).unwrap();
+    std::thread::sleep(std::time::Duration::from_secs(2));
+
+    let mut keys: Vec<&str> = kv.keys_ref().collect();
+    keys.sort();
+    assert_eq!(keys, vec!["a", "b"]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_store_key_accessors() {
+    let mut kv = TinyKV::new();
+    kv.set_by(42u64, "answer".to_string()).unwrap();
+    kv.set_by(&[0xde, 0xad][..], "bytes".to_string()).unwrap();
+
+    let answer: Option<String> = kv.get_by(42u64).unwrap();
+    assert_eq!(answer.as_deref(), Some("answer"));
+    let bytes: Option<String> = kv.get("dead").unwrap();
+    assert_eq!(bytes.as_deref(), Some("bytes"));
+
+    assert!(kv.remove_by(42u64).unwrap());
+    assert!(!kv.contains_key("42"));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_insert_new() {
+    use crate::TinyKVError;
+
+    let mut kv = TinyKV::new();
+    kv.insert_new("id", 1).unwrap();
+    let err = kv.insert_new("id", 2).unwrap_err();
+    assert!(matches!(err, TinyKVError::KeyExists(ref key) if key == "id"));
+    let id: i32 = kv.get("id").unwrap().unwrap();
+    assert_eq!(id, 1);
+
+    kv.set_with_ttl("stale", 1, 0).unwrap();
+    std::thread::sleep(std::time::Duration::from_secs(2));
+    kv.insert_new("stale", 2).unwrap();
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_get_many_results() {
+    let mut kv = TinyKV::new();
+    kv.set("a", 1).unwrap();
+    kv.set("b", "text".to_string()).unwrap();
+
+    let results = kv.get_many_results::<i32>(&["a", "b", "c"]);
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].0, "a");
+    assert!(matches!(results[0].1, Ok(Some(1))));
+    assert!(results[1].1.is_err());
+    assert!(matches!(results[2].1, Ok(None)));
+}
+
 #[cfg(feature = "std")]
 #[test]
 fn test_basic_operations() {
@@ -138,6 +265,57 @@ fn test_ttl() {
     assert!(val.is_none());
 }
 
+#[cfg(all(feature = "rand", not(feature = "nanoserde")))]
+#[test]
+fn test_set_with_ttl_jitter() {
+    let mut kv = TinyKV::new();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    for i in 0..20 {
+        kv.set_with_ttl_jitter(&format!("key{}", i), i, 100, 50).unwrap();
+    }
+    kv.set_with_ttl_jitter("fixed", 0, 100, 0).unwrap();
+
+    for (key, expiry) in kv.all_expiries() {
+        let ttl = expiry.unwrap() - now;
+        if key == "fixed" {
+            assert!((100..=101).contains(&ttl));
+        } else {
+            assert!((100..151).contains(&ttl));
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn test_expiry_notification() {
+    use std::sync::{Arc, Mutex};
+
+    // File-backed, so the waiter also re-checks the expiry on disk.
+    let dir = tempfile::tempdir().unwrap();
+    let store = TinyKV::open(dir.path().join("store.json")).unwrap().with_auto_save();
+    let kv = Arc::new(Mutex::new(store));
+    {
+        let mut kv = kv.lock().unwrap();
+        kv.set_with_ttl("session", "abc".to_string(), 1).unwrap();
+        kv.set("forever", 1).unwrap();
+    }
+
+    // The spawned waiter doesn't borrow the store, so it can still be written meanwhile.
+    let started = std::time::Instant::now();
+    let waiter = tokio::spawn(TinyKV::expiry_notification(Arc::clone(&kv), "session"));
+    kv.lock().unwrap().set("other", 2).unwrap();
+    waiter.await.unwrap();
+    assert!(started.elapsed() >= std::time::Duration::from_secs(1));
+    assert!(kv.lock().unwrap().get::<String>("session").unwrap().is_none());
+
+    TinyKV::expiry_notification(Arc::clone(&kv), "forever").await;
+    TinyKV::expiry_notification(kv, "missing").await;
+}
+
 #[cfg(feature = "std")]
 #[test]
 fn test_auto_save() {
@@ -179,3 +357,2361 @@ fn test_backup() {
     let backup_path = temp_path.with_extension("bak");
     assert!(backup_path.exists());
 }
+
+#[cfg(all(feature = "std", not(feature = "nanoserde")))]
+#[test]
+fn test_value_validator() {
+    use crate::TinyKVError;
+
+    let mut kv = TinyKV::new().with_value_validator(|v| v.get("id").is_some());
+
+    kv.set("ok", serde_json::json!({ "id": 1 })).unwrap();
+    let err = kv.set("bad", serde_json::json!({ "name": "x" })).unwrap_err();
+    assert!(matches!(err, TinyKVError::ValidationFailed(ref key) if key == "bad"));
+    assert!(!kv.contains_key("bad"));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_save_replaces_existing_file() {
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    let temp_path = temp_file.path().to_path_buf();
+
+    let mut kv = TinyKV::open(&temp_path).unwrap();
+    for i in 0..3 {
+        kv.set("counter", i).unwrap();
+        kv.save().unwrap();
+    }
+
+    let mut reopened = TinyKV::open(&temp_path).unwrap();
+    let counter: i32 = reopened.get("counter").unwrap().unwrap();
+    assert_eq!(counter, 2);
+    assert!(!temp_path.with_extension("tmp").exists());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_failed_save_removes_temp_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+
+    let mut kv = TinyKV::open(&path).unwrap();
+    kv.set("key", "value".to_string()).unwrap();
+
+    // A non-empty directory at the target path makes the final rename fail.
+    std::fs::create_dir(&path).unwrap();
+    std::fs::write(path.join("occupied"), "x").unwrap();
+
+    assert!(kv.save().is_err());
+    assert!(!path.with_extension("tmp").exists());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_fsync_interval() {
+    use crate::Op;
+    use std::sync::{Arc, Mutex};
+
+    let fsyncs = Arc::new(Mutex::new(0));
+    let counter = Arc::clone(&fsyncs);
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    let mut kv = TinyKV::open(temp_file.path())
+        .unwrap()
+        .with_auto_save()
+        .with_fsync_interval(std::time::Duration::from_secs(60))
+        .with_observer(Box::new(move |op, _| {
+            if op == Op::Fsync {
+                *counter.lock().unwrap() += 1;
+            }
+        }));
+
+    for i in 0..3 {
+        kv.set("counter", i).unwrap();
+    }
+    // Only the first save falls outside the interval.
+    assert_eq!(*fsyncs.lock().unwrap(), 1);
+
+    let mut reopened = TinyKV::open(temp_file.path()).unwrap();
+    let counter: i32 = reopened.get("counter").unwrap().unwrap();
+    assert_eq!(counter, 2);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_create_dirs() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nested").join("data").join("store.json");
+
+    let mut kv = TinyKV::open(&path).unwrap().with_create_dirs();
+    kv.set("key", "value".to_string()).unwrap();
+    kv.save().unwrap();
+
+    assert!(path.exists());
+}
+
+#[cfg(all(feature = "std", not(feature = "nanoserde")))]
+#[test]
+fn test_get_or_compute() {
+    let mut kv = TinyKV::new();
+    let mut calls = 0;
+
+    let first: i32 = kv
+        .get_or_compute("answer", Some(3600), || {
+            calls += 1;
+            42
+        })
+        .unwrap();
+    let second: i32 = kv
+        .get_or_compute("answer", Some(3600), || {
+            calls += 1;
+            0
+        })
+        .unwrap();
+    assert_eq!((first, second, calls), (42, 42, 1));
+    assert!(kv.all_expiries()[0].1.is_some());
+
+    kv.set_with_ttl("stale", 1, 0).unwrap();
+    std::thread::sleep(std::time::Duration::from_secs(2));
+    let refreshed: i32 = kv.get_or_compute("stale", None, || 2).unwrap();
+    assert_eq!(refreshed, 2);
+}
+
+#[cfg(all(feature = "std", not(feature = "nanoserde")))]
+#[test]
+fn test_get_cached() {
+    use std::sync::Arc;
+
+    let mut kv = TinyKV::new().with_read_cache();
+    kv.set("config", vec![1, 2, 3]).unwrap();
+
+    let first: Arc<Vec<i32>> = kv.get_cached("config").unwrap().unwrap();
+    let second: Arc<Vec<i32>> = kv.get_cached("config").unwrap().unwrap();
+    assert!(Arc::ptr_eq(&first, &second));
+    assert_eq!(kv.entry_metadata("config").unwrap().hits, 2);
+
+    kv.set("config", vec![4]).unwrap();
+    let updated: Arc<Vec<i32>> = kv.get_cached("config").unwrap().unwrap();
+    assert_eq!(*updated, vec![4]);
+
+    let as_text: Option<Arc<Vec<String>>> = kv.get_cached("config").ok().flatten();
+    assert!(as_text.is_none());
+    assert!(kv.get_cached::<i32>("missing").unwrap().is_none());
+}
+
+#[cfg(all(feature = "std", not(feature = "nanoserde")))]
+#[test]
+fn test_read_cache_invalidated_in_bulk() {
+    use std::sync::Arc;
+
+    let mut kv = TinyKV::new().with_read_cache();
+    kv.set("config", vec![1, 2, 3]).unwrap();
+    let snap = kv.snapshot();
+
+    let first: Arc<Vec<i32>> = kv.get_cached("config").unwrap().unwrap();
+    kv.restore(snap).unwrap();
+    let restored: Arc<Vec<i32>> = kv.get_cached("config").unwrap().unwrap();
+    assert!(!Arc::ptr_eq(&first, &restored));
+
+    kv.clear().unwrap();
+    kv.set("config", vec![1, 2, 3]).unwrap();
+    let refilled: Arc<Vec<i32>> = kv.get_cached("config").unwrap().unwrap();
+    assert!(!Arc::ptr_eq(&restored, &refilled));
+
+    kv.clear_prefix("con").unwrap();
+    kv.set("config", vec![1, 2, 3]).unwrap();
+    let after_prefix: Arc<Vec<i32>> = kv.get_cached("config").unwrap().unwrap();
+    assert!(!Arc::ptr_eq(&refilled, &after_prefix));
+}
+
+#[cfg(all(feature = "csv", not(feature = "nanoserde")))]
+#[test]
+fn test_csv_round_trip() {
+    let mut kv = TinyKV::new().with_namespace("app");
+    kv.set("greeting", "hello, \"world\"").unwrap();
+    kv.set("name", "alice").unwrap();
+    kv.set("count", 3).unwrap();
+
+    let mut csv = Vec::new();
+    assert_eq!(kv.export_csv(&mut csv).unwrap(), 2);
+    assert_eq!(
+        String::from_utf8(csv.clone()).unwrap(),
+        "greeting,\"hello, \"\"world\"\"\"\nname,alice\n"
+    );
+
+    let mut imported = TinyKV::new();
+    assert_eq!(imported.import_csv(csv.as_slice()).unwrap(), 2);
+    let greeting: String = imported.get("greeting").unwrap().unwrap();
+    assert_eq!(greeting, "hello, \"world\"");
+
+    assert!(imported.import_csv("a,b,c\n".as_bytes()).is_err());
+    assert_eq!(imported.keys().len(), 2);
+}
+
+#[cfg(all(feature = "csv", not(feature = "nanoserde")))]
+#[test]
+fn test_csv_entries_round_trip() {
+    let mut kv = TinyKV::new().with_namespace("app");
+    kv.set("greeting", "hello, \"world\"").unwrap();
+    kv.set("count", 3).unwrap();
+    kv.set_with_expiry("session", vec![1, 2], 4_000_000_000).unwrap();
+
+    let mut csv = Vec::new();
+    assert_eq!(kv.export_csv_entries(&mut csv).unwrap(), 3);
+    assert_eq!(
+        String::from_utf8(csv.clone()).unwrap(),
+        concat!(
+            "count,3,\n",
+            r#"greeting,"""hello, \""world\""""","#,
+            "\n",
+            r#"session,"[1,2]",4000000000"#,
+            "\n"
+        )
+    );
+
+    let mut imported = TinyKV::new();
+    let stale = "old,1,1\n";
+    let rows = [csv.as_slice(), stale.as_bytes()].concat();
+    assert_eq!(imported.import_csv_entries(rows.as_slice()).unwrap(), 3);
+    assert_eq!(imported.get::<String>("greeting").unwrap().unwrap(), "hello, \"world\"");
+    assert_eq!(imported.get::<Vec<i32>>("session").unwrap(), Some(vec![1, 2]));
+    assert_eq!(imported.entry_metadata("session").unwrap().expires_at, Some(4_000_000_000));
+    assert!(!imported.contains_key("old"));
+
+    assert!(imported.import_csv_entries("a,b\n".as_bytes()).is_err());
+    assert!(imported.import_csv_entries("a,not json,\n".as_bytes()).is_err());
+    assert_eq!(imported.keys().len(), 3);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.log");
+    {
+        let mut log = TinyKV::open_append_log(&path).unwrap();
+        assert_eq!(log.import_csv_entries(csv.as_slice()).unwrap(), 3);
+    }
+    let reopened = TinyKV::open_append_log(&path).unwrap();
+    assert_eq!(reopened.entry_metadata("session").unwrap().expires_at, Some(4_000_000_000));
+    assert_eq!(reopened.keys().len(), 3);
+}
+
+#[cfg(all(feature = "compression", not(feature = "nanoserde")))]
+#[test]
+fn test_compress_above() {
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    let big = "x".repeat(1000);
+
+    let mut kv = TinyKV::open(temp_file.path())
+        .unwrap()
+        .with_auto_save()
+        .with_compress_above(64);
+    kv.set("big", &big).unwrap();
+    kv.set("small", "tiny").unwrap();
+
+    let on_disk = std::fs::read_to_string(temp_file.path()).unwrap();
+    assert!(!on_disk.contains(&big));
+    assert!(on_disk.contains("\"tiny\""));
+    assert_eq!(on_disk.matches("\"compressed\"").count(), 1);
+
+    let mut reopened = TinyKV::open(temp_file.path()).unwrap();
+    let value: String = reopened.get("big").unwrap().unwrap();
+    assert_eq!(value, big);
+    assert_eq!(reopened.keys_with_value(&big).unwrap(), vec!["big".to_string()]);
+}
+
+#[cfg(all(feature = "std", not(feature = "nanoserde")))]
+#[test]
+fn test_parse_error_recovery() {
+    let mut kv = TinyKV::new().with_parse_error_recovery(Box::new(|_| serde_json::json!(0)));
+    kv.set("good", 5).unwrap();
+    kv.set("bad", "not a number").unwrap();
+
+    let bad: i32 = kv.get("bad").unwrap().unwrap();
+    assert_eq!(bad, 0);
+
+    let mut total = 0;
+    kv.for_each_value(|_, value: i32| total += value).unwrap();
+    assert_eq!(total, 5);
+
+    let raw: String = kv.get("bad").unwrap().unwrap();
+    assert_eq!(raw, "not a number");
+}
+
+#[cfg(all(feature = "std", not(feature = "nanoserde")))]
+#[test]
+fn test_for_each_value() {
+    let mut kv = TinyKV::new().with_namespace("ns");
+    kv.set("a", 1).unwrap();
+    kv.set("b", 2).unwrap();
+
+    let mut seen = Vec::new();
+    kv.for_each_value(|key, value: i32| seen.push((key, value))).unwrap();
+    seen.sort();
+    assert_eq!(seen, vec![("a".to_string(), 1), ("b".to_string(), 2)]);
+
+    kv.set("text", "x").unwrap();
+    assert!(kv.for_each_value(|_, _: i32| {}).is_err());
+}
+
+#[cfg(all(feature = "std", not(feature = "nanoserde")))]
+#[test]
+fn test_entries_ordered() {
+    let mut kv = TinyKV::new().with_namespace("ns");
+    for key in ["b", "c", "a"] {
+        kv.set(key, key).unwrap();
+    }
+
+    let forward: Vec<&str> = kv.entries_ordered().map(|(key, _)| key).collect();
+    assert_eq!(forward, vec!["a", "b", "c"]);
+    let backward: Vec<&str> = kv.entries_rev().map(|(key, _)| key).collect();
+    assert_eq!(backward, vec!["c", "b", "a"]);
+
+    let mut both = kv.entries_ordered();
+    assert_eq!(*both.next().unwrap().1, "a");
+    assert_eq!(*both.next_back().unwrap().1, "c");
+    assert_eq!(both.len(), 1);
+}
+
+#[cfg(all(feature = "compression", not(feature = "nanoserde")))]
+#[test]
+fn test_entries_ordered_decompresses() {
+    let big = "x".repeat(1000);
+    let mut kv = TinyKV::new().with_compress_above(64);
+    kv.set("big", &big).unwrap();
+    kv.set("small", "tiny").unwrap();
+
+    let values: Vec<_> = kv.entries_ordered().map(|(_, value)| value.into_owned()).collect();
+    assert_eq!(values, vec![serde_json::json!(big), serde_json::json!("tiny")]);
+    assert_eq!(*kv.entries_rev().next().unwrap().1, "tiny");
+}
+
+#[cfg(all(feature = "std", not(feature = "nanoserde")))]
+#[test]
+fn test_export_chunk() {
+    let mut kv = TinyKV::new().with_namespace("app");
+    for i in 0..5 {
+        kv.set(&format!("key{i}"), i).unwrap();
+    }
+
+    let first: Vec<(String, i32)> = kv.export_chunk(2).unwrap();
+    assert_eq!(first, vec![("key0".to_string(), 0), ("key1".to_string(), 1)]);
+    let second: Vec<(String, i32)> = kv.export_chunk(2).unwrap();
+    assert_eq!(second.len(), 2);
+    let third: Vec<(String, i32)> = kv.export_chunk(2).unwrap();
+    assert_eq!(third, vec![("key4".to_string(), 4)]);
+    assert!(kv.export_chunk::<i32>(2).unwrap().is_empty());
+
+    kv.reset_export_cursor();
+    let again: Vec<(String, i32)> = kv.export_chunk(1).unwrap();
+    assert_eq!(again, vec![("key0".to_string(), 0)]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_opportunistic_purge() {
+    let mut kv = TinyKV::new().with_opportunistic_purge();
+    kv.set_with_ttl("stale", "old".to_string(), 0).unwrap();
+    std::thread::sleep(std::time::Duration::from_secs(2));
+
+    // Every call examines one slot, so a couple of writes reach the expired entry.
+    for i in 0..3 {
+        kv.set(&format!("fresh{i}"), "new".to_string()).unwrap();
+    }
+    assert!(!kv.to_data().unwrap().contains("stale"));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_opportunistic_purge_visits_every_key() {
+    let entries: Vec<String> = (0..10)
+        .flat_map(|i| {
+            [
+                format!(r#""stale{i}": {{"value": "{i}", "expires_at": 1}}"#),
+                format!(r#""live{i}": {{"value": "{i}", "expires_at": null}}"#),
+            ]
+        })
+        .collect();
+    let mut kv = TinyKV::from_data(&format!("{{{}}}", entries.join(",")))
+        .unwrap()
+        .with_opportunistic_purge();
+
+    // One key is examined per call, and each of the 20 keys exactly once per round.
+    for _ in 0..20 {
+        assert!(kv.get::<i32>("missing").unwrap().is_none());
+    }
+    assert!(!kv.to_data().unwrap().contains("stale"));
+    assert_eq!(kv.keys().len(), 10);
+}
+
+#[cfg(all(feature = "std", not(feature = "nanoserde")))]
+#[test]
+fn test_count_deserializable_as() {
+    let mut kv = TinyKV::new();
+    kv.set("a", 1).unwrap();
+    kv.set("b", 2).unwrap();
+    kv.set("c", "three").unwrap();
+
+    assert_eq!(kv.count_deserializable_as::<i64>(), 2);
+    assert_eq!(kv.count_deserializable_as::<String>(), 1);
+}
+
+#[cfg(all(feature = "std", not(feature = "nanoserde")))]
+#[test]
+fn test_replace_returns_previous_value() {
+    let mut kv = TinyKV::new();
+
+    let first: Option<i32> = kv.replace("counter", 1).unwrap();
+    assert_eq!(first, None);
+    let second: Option<i32> = kv.replace("counter", 2).unwrap();
+    assert_eq!(second, Some(1));
+
+    let current: i32 = kv.get("counter").unwrap().unwrap();
+    assert_eq!(current, 2);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_gc_stats() {
+    let mut kv = TinyKV::new();
+    kv.set("keep", "value".to_string()).unwrap();
+    kv.set_with_ttl("stale", "old".to_string(), 0).unwrap();
+    std::thread::sleep(std::time::Duration::from_secs(2));
+
+    let stats = kv.gc_stats();
+    assert_eq!(stats.total_entries, 2);
+    assert_eq!(stats.expired_entries, 1);
+    assert!(stats.reclaimable_bytes > 0);
+
+    kv.purge_expired().unwrap();
+    assert_eq!(kv.gc_stats().reclaimable_bytes, 0);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_set_option() {
+    let mut kv = TinyKV::new();
+
+    kv.set_option("nickname", Some("al".to_string())).unwrap();
+    let nickname: Option<String> = kv.get("nickname").unwrap();
+    assert_eq!(nickname, Some("al".to_string()));
+
+    kv.set_option::<String>("nickname", None).unwrap();
+    assert!(!kv.contains_key("nickname"));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_backup_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+    let backup_dir = dir.path().join("backups");
+
+    let mut kv = TinyKV::open(&path)
+        .unwrap()
+        .with_backup(true)
+        .with_backup_dir(backup_dir.clone());
+    kv.set("key", "value".to_string()).unwrap();
+    kv.save().unwrap();
+    kv.save().unwrap();
+
+    assert!(backup_dir.join("store.json.bak").exists());
+    assert!(!path.with_extension("bak").exists());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_save_skips_unchanged_content() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+
+    let mut kv = TinyKV::open(&path).unwrap().with_backup(true);
+    kv.set("key", "value".to_string()).unwrap();
+    assert!(kv.save_if_changed().unwrap());
+    assert!(!kv.save_if_changed().unwrap());
+    assert!(!path.with_extension("bak").exists());
+
+    kv.set("key", "other".to_string()).unwrap();
+    assert!(kv.save_if_changed().unwrap());
+    assert!(path.with_extension("bak").exists());
+
+    kv.set("key", "changed".to_string()).unwrap();
+    kv.set("key", "other".to_string()).unwrap();
+    assert!(!kv.save_if_changed().unwrap());
+
+    let _: Option<String> = kv.get("key").unwrap();
+    assert!(!kv.save_if_changed().unwrap());
+
+    std::fs::remove_file(&path).unwrap();
+    assert!(kv.save_if_changed().unwrap());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_checkpoints() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+
+    let mut kv = TinyKV::open(&path).unwrap().with_auto_save();
+    kv.set("step", 1).unwrap();
+    let first = kv.checkpoint().unwrap();
+    kv.set("step", 2).unwrap();
+    let second = kv.checkpoint().unwrap();
+    kv.set("step", 3).unwrap();
+    assert!(first < second);
+    assert_eq!(kv.checkpoints().unwrap(), vec![first, second]);
+
+    assert!(kv.restore_checkpoint(first).unwrap());
+    let step: i32 = kv.get("step").unwrap().unwrap();
+    assert_eq!(step, 1);
+
+    assert_eq!(kv.prune_checkpoints(1).unwrap(), 1);
+    assert_eq!(kv.checkpoints().unwrap(), vec![second]);
+    assert!(!kv.restore_checkpoint(first).unwrap());
+
+    assert!(TinyKV::new().checkpoint().is_err());
+}
+
+#[cfg(feature = "file-lock")]
+#[test]
+fn test_restore_checkpoint_waits_for_lock() {
+    use crate::TinyKVError;
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+
+    let mut kv = TinyKV::open(&path)
+        .unwrap()
+        .with_auto_save()
+        .with_lock_timeout(Duration::from_millis(50));
+    kv.set("step", 1).unwrap();
+    let first = kv.checkpoint().unwrap();
+    kv.set("step", 2).unwrap();
+
+    let holder = TinyKV::open_locked(&path).unwrap();
+    assert!(matches!(kv.restore_checkpoint(first), Err(TinyKVError::Locked)));
+    assert!(std::fs::read_to_string(&path).unwrap().contains('2'));
+    assert!(!dir.path().join("store.tmp").exists());
+
+    drop(holder);
+    assert!(kv.restore_checkpoint(first).unwrap());
+    assert_eq!(kv.get::<i32>("step").unwrap(), Some(1));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_restore_from_backup() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+
+    let mut kv = TinyKV::open(&path).unwrap().with_backup(true);
+    assert!(!kv.restore_from_backup().unwrap());
+
+    kv.set("key", "good".to_string()).unwrap();
+    kv.save().unwrap();
+    kv.set("key", "bad".to_string()).unwrap();
+    kv.save().unwrap();
+
+    assert!(kv.restore_from_backup().unwrap());
+    let value: String = kv.get("key").unwrap().unwrap();
+    assert_eq!(value, "good");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_set_with_expiry() {
+    use crate::TinyKVError;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let mut kv = TinyKV::new();
+
+    kv.set_with_expiry("future", "value".to_string(), now + 60).unwrap();
+    assert!(kv.contains_key("future"));
+
+    let err = kv.set_with_expiry("past", "value".to_string(), now - 60).unwrap_err();
+    assert!(matches!(err, TinyKVError::ExpiryInPast(ts) if ts == now - 60));
+    assert!(!kv.contains_key("past"));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_set_in_get_in() {
+    let mut kv = TinyKV::new().with_namespace("app");
+
+    kv.set_in("other", "shared", "value".to_string()).unwrap();
+    let value: Option<String> = kv.get_in("other", "shared").unwrap();
+    assert_eq!(value, Some("value".to_string()));
+
+    // The default namespace is untouched by the override.
+    assert!(!kv.contains_key("shared"));
+    assert!(kv.keys().is_empty());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_acquire_lock() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+
+    let mut holder = TinyKV::open(&path).unwrap();
+    let mut contender = TinyKV::open(&path).unwrap();
+
+    {
+        let mut guard = holder.acquire_lock("job", 60).unwrap().unwrap();
+        guard.set("progress", 1).unwrap();
+        assert!(contender.acquire_lock("job", 60).unwrap().is_none());
+    }
+
+    // Dropping the guard released the lock on disk.
+    assert!(contender.acquire_lock("job", 60).unwrap().is_some());
+
+    // A guard whose lock was taken over leaves the new holder's lock alone.
+    let guard = holder.acquire_lock("job", 60).unwrap().unwrap();
+    contender.set("__lock:job", "takeover".to_string()).unwrap();
+    contender.save().unwrap();
+    drop(guard);
+    contender.reload().unwrap();
+    assert_eq!(contender.get::<String>("__lock:job").unwrap().as_deref(), Some("takeover"));
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "file-lock"))]
+#[test]
+fn test_acquire_lock_is_exclusive_across_stores() {
+    use std::sync::{Arc, Barrier};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+    let barrier = Arc::new(Barrier::new(8));
+
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let path = path.clone();
+            let barrier = Arc::clone(&barrier);
+            std::thread::spawn(move || {
+                let mut kv = TinyKV::open(&path).unwrap();
+                let guard = kv.acquire_lock("job", 60).unwrap();
+                let acquired = guard.is_some();
+                // Keep every winner's guard alive until all contenders have tried.
+                barrier.wait();
+                acquired
+            })
+        })
+        .collect();
+    let winners = handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .filter(|&acquired| acquired)
+        .count();
+    assert_eq!(winners, 1);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_coalesce_window() {
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    let temp_path = temp_file.path().to_path_buf();
+
+    let mut kv = TinyKV::open(&temp_path)
+        .unwrap()
+        .with_auto_save()
+        .with_coalesce_window(std::time::Duration::from_secs(60));
+    for i in 0..5 {
+        kv.set("counter", i).unwrap();
+    }
+
+    // Only the first write reached disk; the rest are held in memory.
+    let mut on_disk = TinyKV::open(&temp_path).unwrap();
+    let counter: i32 = on_disk.get("counter").unwrap().unwrap();
+    assert_eq!(counter, 0);
+
+    kv.save().unwrap();
+    on_disk.reload().unwrap();
+    let counter: i32 = on_disk.get("counter").unwrap().unwrap();
+    assert_eq!(counter, 4);
+
+    // The window is per key: a write to another key saves, carrying pending writes along.
+    kv.set("counter", 5).unwrap();
+    kv.set("other", 1).unwrap();
+    on_disk.reload().unwrap();
+    assert_eq!(on_disk.get::<i32>("counter").unwrap(), Some(5));
+    assert_eq!(on_disk.get::<i32>("other").unwrap(), Some(1));
+
+    // Lock writes are never held back, even when re-acquired within the window.
+    drop(kv.acquire_lock("job", 60).unwrap());
+    let lock = kv.acquire_lock("job", 60).unwrap();
+    assert!(lock.is_some());
+    on_disk.reload().unwrap();
+    assert!(on_disk.contains_key("__lock:job"));
+    drop(lock);
+
+    // Drop flushes whatever the window still holds back.
+    kv.set("counter", 7).unwrap();
+    drop(kv);
+    on_disk.reload().unwrap();
+    assert_eq!(on_disk.get::<i32>("counter").unwrap(), Some(7));
+}
+
+#[cfg(all(feature = "std", not(feature = "nanoserde")))]
+#[test]
+fn test_entry_json_round_trip() {
+    use crate::Entry;
+
+    let entry = Entry {
+        value: serde_json::json!({ "name": "alice" }),
+        expires_at: Some(42),
+        modified_at: None,
+        created_at: None,
+        accessed_at: None,
+        hits: 0,
+        compressed: false,
+        inserted_seq: 0,
+        used_seq: 0,
+    };
+    let json = entry.to_json().unwrap();
+    let parsed = Entry::from_json(&json).unwrap();
+
+    assert_eq!(parsed.value, entry.value);
+    assert_eq!(parsed.expires_at, Some(42));
+    assert!(!json.contains("hits"));
+    assert!(Entry::from_json("not json").is_err());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_clear_memory_skips_save() {
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    let temp_path = temp_file.path().to_path_buf();
+
+    let mut kv = TinyKV::open(&temp_path).unwrap().with_auto_save();
+    kv.set("key", "value".to_string()).unwrap();
+    kv.clear_memory();
+    assert!(kv.is_empty());
+
+    let mut on_disk = TinyKV::open(&temp_path).unwrap();
+    let value: Option<String> = on_disk.get("key").unwrap();
+    assert_eq!(value, Some("value".to_string()));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_append_log() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.log");
+
+    {
+        let mut log = TinyKV::open_append_log(&path).unwrap();
+        log.set("event1", "start".to_string()).unwrap();
+        log.set("event2", "stop".to_string()).unwrap();
+        log.set("event1", "restart".to_string()).unwrap();
+    }
+
+    // One record line per write; replay keeps the latest value per key.
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(contents.lines().count(), 3);
+
+    let mut reopened = TinyKV::open_append_log(&path).unwrap();
+    let event1: String = reopened.get("event1").unwrap().unwrap();
+    assert_eq!(event1, "restart");
+    assert_eq!(reopened.len(), 2);
+
+    reopened.save().unwrap();
+    let compacted = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(compacted.lines().count(), 2);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_keys_modified_after() {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    let mut kv = TinyKV::new().with_namespace("sync");
+    kv.set("old", 1).unwrap();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+    assert_eq!(kv.keys_modified_after(now), vec!["old".to_string()]);
+    assert!(kv.keys_modified_after(now + 60).is_empty());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_serialization_hooks() {
+    const HEADER: &str = "# tinykv\n";
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+
+    let mut kv = TinyKV::open(&path)
+        .unwrap()
+        .with_pre_save(Box::new(|json| format!("{HEADER}{json}")));
+    kv.set("key", "value".to_string()).unwrap();
+    kv.save().unwrap();
+    assert!(std::fs::read_to_string(&path).unwrap().starts_with(HEADER));
+
+    let mut reopened = TinyKV::new()
+        .with_post_load(Box::new(|contents| contents.trim_start_matches(HEADER).to_string()))
+        .load_from(&path)
+        .unwrap();
+    let value: String = reopened.get("key").unwrap().unwrap();
+    assert_eq!(value, "value");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_try_get() {
+    let mut kv = TinyKV::new();
+    kv.set("name", "alice".to_string()).unwrap();
+
+    assert_eq!(kv.try_get::<String>("name"), Some("alice".to_string()));
+    assert_eq!(kv.try_get::<String>("missing"), None);
+    // Wrong type fails to deserialize and is swallowed.
+    assert_eq!(kv.try_get::<i32>("name"), None);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_all_expiries() {
+    let mut kv = TinyKV::new().with_namespace("jobs");
+    kv.set("forever", 1).unwrap();
+    kv.set_with_expiry("scheduled", 2, u64::MAX).unwrap();
+
+    let mut expiries = kv.all_expiries();
+    expiries.sort();
+    assert_eq!(
+        expiries,
+        vec![
+            ("forever".to_string(), None),
+            ("scheduled".to_string(), Some(u64::MAX)),
+        ]
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_auto_purge_ratio() {
+    let mut kv = TinyKV::new().with_auto_purge_ratio(0.5);
+    kv.set_with_ttl("a", 1, 0).unwrap();
+    kv.set_with_ttl("b", 2, 0).unwrap();
+    std::thread::sleep(std::time::Duration::from_secs(2));
+
+    // Two of three entries are expired, which crosses the 0.5 ratio.
+    kv.set("fresh", 3).unwrap();
+    let data = kv.to_data().unwrap();
+    assert!(!data.contains("\"a\""));
+    assert!(!data.contains("\"b\""));
+    assert!(data.contains("fresh"));
+}
+
+#[cfg(all(feature = "std", not(feature = "nanoserde")))]
+#[test]
+fn test_auto_purge_ratio_counts_every_ttl_write() {
+    let mut kv = TinyKV::new().with_auto_purge_ratio(0.5);
+    kv.set_many_with_ttl(vec![
+        ("a".to_string(), 1, Some(0)),
+        ("b".to_string(), 2, Some(0)),
+    ])
+    .unwrap();
+    // Removing and persisting TTL entries must not leave them counted.
+    kv.set_with_ttl("gone", 0, 60).unwrap();
+    kv.remove("gone").unwrap();
+    kv.set_with_ttl("kept", 0, 60).unwrap();
+    kv.persist("kept").unwrap();
+    std::thread::sleep(std::time::Duration::from_secs(2));
+
+    kv.set("fresh", 3).unwrap();
+    let data = kv.to_data().unwrap();
+    assert!(!data.contains("\"a\""));
+    assert!(!data.contains("\"b\""));
+    assert!(data.contains("kept"));
+}
+
+#[cfg(all(feature = "std", not(feature = "nanoserde")))]
+#[test]
+fn test_for_each_mut() {
+    let mut kv = TinyKV::new().with_namespace("app");
+    kv.set("a", 1).unwrap();
+    kv.set("b", 2).unwrap();
+    kv.set("c", "text").unwrap();
+
+    let modified = kv
+        .for_each_mut(|_, value| match value.as_i64() {
+            Some(n) => {
+                *value = serde_json::json!(n * 10);
+                true
+            }
+            None => false,
+        })
+        .unwrap();
+
+    assert_eq!(modified, 2);
+    let b: i64 = kv.get("b").unwrap().unwrap();
+    assert_eq!(b, 20);
+    let c: String = kv.get("c").unwrap().unwrap();
+    assert_eq!(c, "text");
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn test_open_reads_gzipped_file() {
+    use std::io::Write;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json.gz");
+
+    let mut source = TinyKV::new();
+    source.set("key", "value".to_string()).unwrap();
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(source.to_data().unwrap().as_bytes()).unwrap();
+    std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+
+    let mut kv = TinyKV::open(&path).unwrap();
+    let value: String = kv.get("key").unwrap().unwrap();
+    assert_eq!(value, "value");
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_set_many_with_ttl() {
+    let mut kv = TinyKV::new();
+    kv.set_many_with_ttl(vec![
+        ("session1".to_string(), "alice".to_string(), Some(60)),
+        ("session2".to_string(), "bob".to_string(), None),
+    ])
+    .unwrap();
+
+    let mut expiries = kv.all_expiries();
+    expiries.sort();
+    assert_eq!(expiries.len(), 2);
+    assert!(expiries[0].1.is_some());
+    assert_eq!(expiries[1], ("session2".to_string(), None));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_remove_returning() {
+    let mut kv = TinyKV::new();
+    kv.set("draft", "hello".to_string()).unwrap();
+
+    let removed: Option<String> = kv.remove_returning("draft").unwrap();
+    assert_eq!(removed, Some("hello".to_string()));
+    assert!(!kv.contains_key("draft"));
+
+    let missing: Option<String> = kv.remove_returning("draft").unwrap();
+    assert_eq!(missing, None);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_append_log_tombstones() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.log");
+
+    {
+        let mut log = TinyKV::open_append_log(&path).unwrap();
+        log.set("keep", "1".to_string()).unwrap();
+        log.set("gone", "2".to_string()).unwrap();
+        log.set("tmp:a", "3".to_string()).unwrap();
+        log.set("old", "4".to_string()).unwrap();
+        assert!(log.remove("gone").unwrap());
+        assert_eq!(log.clear_prefix("tmp:").unwrap(), 1);
+        assert!(log.rename_key("old", "new").unwrap());
+    }
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(contents.contains(r#"{"key":"gone","deleted":true}"#));
+
+    let mut reopened = TinyKV::open_append_log(&path).unwrap();
+    assert!(!reopened.contains_key("gone"));
+    assert!(!reopened.contains_key("tmp:a"));
+    assert!(!reopened.contains_key("old"));
+    let moved: String = reopened.get("new").unwrap().unwrap();
+    assert_eq!(moved, "4");
+    assert_eq!(reopened.len(), 2);
+
+    // Re-setting a removed key after its tombstone brings it back on replay.
+    reopened.set("gone", "5".to_string()).unwrap();
+    drop(reopened);
+    assert!(TinyKV::open_append_log(&path).unwrap().contains_key("gone"));
+
+    let capped_path = dir.path().join("capped.log");
+    let mut capped = TinyKV::open_append_log(&capped_path).unwrap().with_capacity(1);
+    capped.set("a", "1".to_string()).unwrap();
+    capped.set("b", "2".to_string()).unwrap();
+    drop(capped);
+    let capped = TinyKV::open_append_log(&capped_path).unwrap();
+    assert!(!capped.contains_key("a"));
+    assert_eq!(capped.len(), 1);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_set_many_persists_each_key() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.log");
+    let batch = || (1..=3).map(|i| (format!("key{}", i), i.to_string()));
+
+    {
+        let mut log = TinyKV::open_append_log(&path).unwrap();
+        log.set_many(batch()).unwrap();
+    }
+    assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 3);
+    assert_eq!(TinyKV::open_append_log(&path).unwrap().len(), 3);
+
+    let mut capped = TinyKV::new().with_capacity(2);
+    capped.set_many(batch()).unwrap();
+    assert_eq!(capped.len(), 2);
+    assert!(capped.contains_key("key3"));
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_bulk_writes_notify() {
+    use crate::ChangeKind;
+    use std::sync::{Arc, Mutex};
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&events);
+    let mut kv = TinyKV::new();
+    kv.set("a", 1).unwrap();
+    kv.set("b", "text").unwrap();
+    kv.on_change(move |key, kind| recorded.lock().unwrap().push((key.to_string(), kind)));
+
+    kv.for_each_mut(|_, value| match value.as_i64() {
+        Some(n) => {
+            *value = serde_json::json!(n + 1);
+            true
+        }
+        None => false,
+    })
+    .unwrap();
+    let _: Option<String> = kv.remove_returning("b").unwrap();
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            ("a".to_string(), ChangeKind::Set),
+            ("b".to_string(), ChangeKind::Removed),
+        ]
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_raw_len_counts_expired() {
+    let mut kv = TinyKV::new();
+    kv.set("keep", 1).unwrap();
+    kv.set_with_ttl("stale", 2, 0).unwrap();
+    std::thread::sleep(std::time::Duration::from_secs(2));
+
+    assert_eq!(kv.len(), 1);
+    assert_eq!(kv.raw_len(), 2);
+}
+
+#[test]
+fn test_empty_content_is_empty_store() {
+    assert!(TinyKV::from_data("").unwrap().keys().is_empty());
+    assert!(TinyKV::from_data(" \n\t ").unwrap().keys().is_empty());
+
+    #[cfg(feature = "std")]
+    {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("blank.json");
+        std::fs::write(&path, "  \n").unwrap();
+        assert!(TinyKV::open(&path).unwrap().keys().is_empty());
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_len_tracks_ttl_entries() {
+    let mut kv = TinyKV::new();
+    assert!(kv.is_empty());
+    kv.set("plain", 1).unwrap();
+    assert_eq!(kv.len(), 1);
+
+    kv.set_with_ttl("stale", 2, 0).unwrap();
+    std::thread::sleep(std::time::Duration::from_secs(2));
+    assert_eq!(kv.len(), 1);
+
+    let reloaded = TinyKV::from_data(&kv.to_data().unwrap()).unwrap();
+    assert_eq!(reloaded.len(), 1);
+    kv.remove("plain").unwrap();
+    assert!(kv.is_empty());
+
+    kv.clear().unwrap();
+    kv.set("fresh", 3).unwrap();
+    assert_eq!(kv.len(), 1);
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_get_ttl() {
+    use crate::Ttl;
+
+    let stale = r#"{"app:old": {"value": 1, "expires_at": 1}}"#;
+    let mut kv = TinyKV::from_data(stale).unwrap().with_namespace("app");
+    kv.set("forever", 1).unwrap();
+    kv.set_with_ttl("session", 1, 60).unwrap();
+
+    assert_eq!(kv.get_ttl("forever").unwrap(), Some(Ttl::None));
+    assert!(matches!(kv.get_ttl("session").unwrap(), Some(Ttl::Expires(59..=60))));
+    assert_eq!(kv.get_ttl("old").unwrap(), None);
+    assert_eq!(kv.get_ttl("missing").unwrap(), None);
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_extend_ttl() {
+    use crate::Ttl;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("ttl.json");
+    std::fs::write(&path, r#"{"app:old": {"value": 1, "expires_at": 1}}"#).unwrap();
+    let mut kv = TinyKV::open(&path).unwrap().with_namespace("app").with_auto_save();
+    kv.set_with_ttl("session", 1, 60).unwrap();
+    kv.set("forever", 1).unwrap();
+
+    assert!(kv.extend_ttl("session", 100).unwrap());
+    assert!(matches!(kv.get_ttl("session").unwrap(), Some(Ttl::Expires(159..=160))));
+    assert!(!kv.extend_ttl("forever", 100).unwrap());
+    assert!(!kv.extend_ttl("old", 100).unwrap());
+    assert!(!kv.extend_ttl("missing", 100).unwrap());
+
+    let reopened = TinyKV::open(&path).unwrap().with_namespace("app");
+    assert!(matches!(reopened.get_ttl("session").unwrap(), Some(Ttl::Expires(159..=160))));
+    assert_eq!(reopened.get_ttl("old").unwrap(), None);
+
+    // In append-log mode the new expiry is appended as a record.
+    let log_path = dir.path().join("events.log");
+    let mut log = TinyKV::open_append_log(&log_path).unwrap();
+    log.set_with_ttl("session", 1, 60).unwrap();
+    assert!(log.extend_ttl("session", 100).unwrap());
+    drop(log);
+    let reopened = TinyKV::open_append_log(&log_path).unwrap();
+    assert!(matches!(reopened.get_ttl("session").unwrap(), Some(Ttl::Expires(159..=160))));
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_persist() {
+    use crate::Ttl;
+
+    let mut kv = TinyKV::from_data(r#"{"old": {"value": 1, "expires_at": 1}}"#).unwrap();
+    kv.set_with_ttl("cached", 1, 60).unwrap();
+    kv.set("forever", 1).unwrap();
+
+    assert!(kv.persist("cached").unwrap());
+    assert_eq!(kv.get_ttl("cached").unwrap(), Some(Ttl::None));
+    assert!(!kv.persist("cached").unwrap());
+    assert!(!kv.persist("forever").unwrap());
+    assert!(!kv.persist("old").unwrap());
+    assert!(!kv.persist("missing").unwrap());
+    assert!(!kv.contains_key("missing"));
+
+    // In append-log mode the cleared expiry is appended as a record.
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.log");
+    let mut log = TinyKV::open_append_log(&path).unwrap();
+    log.set_with_ttl("cached", 1, 60).unwrap();
+    assert!(log.persist("cached").unwrap());
+    drop(log);
+    let reopened = TinyKV::open_append_log(&path).unwrap();
+    assert_eq!(reopened.get_ttl("cached").unwrap(), Some(Ttl::None));
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_update() {
+    use crate::Ttl;
+
+    let mut kv = TinyKV::new();
+    kv.set_with_ttl("visits", vec![1], 60).unwrap();
+
+    assert!(kv.update("visits", |visits: &mut Vec<i32>| visits.push(2)).unwrap());
+    assert_eq!(kv.get::<Vec<i32>>("visits").unwrap(), Some(vec![1, 2]));
+    assert!(matches!(kv.get_ttl("visits").unwrap(), Some(Ttl::Expires(59..=60))));
+
+    assert!(!kv.update("missing", |_: &mut i32| panic!("not called")).unwrap());
+    assert!(kv.update("visits", |_: &mut String| ()).is_err());
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_increment() {
+    use crate::Ttl;
+
+    let mut kv = TinyKV::new();
+    assert_eq!(kv.increment("hits", 1).unwrap(), 1);
+    assert_eq!(kv.increment("hits", 5).unwrap(), 6);
+    assert_eq!(kv.decrement("hits", 2).unwrap(), 4);
+    assert_eq!(kv.get::<i64>("hits").unwrap(), Some(4));
+
+    kv.set_with_ttl("window", 10, 60).unwrap();
+    assert_eq!(kv.increment("window", 1).unwrap(), 11);
+    assert!(matches!(kv.get_ttl("window").unwrap(), Some(Ttl::Expires(59..=60))));
+
+    kv.set("name", "alice").unwrap();
+    assert!(kv.increment("name", 1).is_err());
+    assert_eq!(kv.get::<String>("name").unwrap().unwrap(), "alice");
+    assert!(kv.increment("hits", i64::MAX).is_err());
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_get_or_insert_with() {
+    use crate::Ttl;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    let mut kv = TinyKV::open(&path).unwrap().with_auto_save();
+
+    let theme: String = kv.get_or_insert_with("theme", || "dark".to_string()).unwrap();
+    assert_eq!(theme, "dark");
+    assert_eq!(kv.get_ttl("theme").unwrap(), Some(Ttl::None));
+    let theme: String = kv.get_or_insert_with("theme", || unreachable!()).unwrap();
+    assert_eq!(theme, "dark");
+
+    let reopened = TinyKV::open(&path).unwrap();
+    assert!(reopened.contains_key("theme"));
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_set_many() {
+    use crate::Op;
+    use std::sync::{Arc, Mutex};
+
+    let dir = tempfile::tempdir().unwrap();
+    let saves = Arc::new(Mutex::new(0));
+    let counter = Arc::clone(&saves);
+    let mut kv = TinyKV::open(dir.path().join("many.json"))
+        .unwrap()
+        .with_namespace("app")
+        .with_auto_save()
+        .with_observer(Box::new(move |op, _| {
+            if op == Op::Save {
+                *counter.lock().unwrap() += 1;
+            }
+        }));
+
+    kv.set_many((0..30).map(|i| (format!("key{i}"), i))).unwrap();
+    assert_eq!(*saves.lock().unwrap(), 1);
+    assert_eq!(kv.len(), 30);
+    assert_eq!(kv.get::<i32>("key7").unwrap(), Some(7));
+    assert_eq!(kv.keys_ref().filter(|key| key.starts_with("key")).count(), 30);
+
+    let mut kv = kv.with_value_validator(|value| value.as_i64() != Some(-1));
+    assert!(kv.set_many(vec![("a".to_string(), 1), ("b".to_string(), -1)]).is_err());
+    assert!(!kv.contains_key("a"));
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_get_many() {
+    let mut kv = TinyKV::from_data(r#"{"app:old": {"value": 0, "expires_at": 1}}"#)
+        .unwrap()
+        .with_namespace("app");
+    kv.set("a", 1).unwrap();
+    kv.set("b", 2).unwrap();
+
+    let values: Vec<Option<i32>> = kv.get_many(&["b", "missing", "old", "a"]).unwrap();
+    assert_eq!(values, vec![Some(2), None, None, Some(1)]);
+    assert_eq!(kv.raw_len(), 2);
+    assert_eq!(kv.entry_metadata("a").unwrap().hits, 1);
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_iter() {
+    let mut kv = TinyKV::from_data(r#"{"app:old": {"value": 0, "expires_at": 1}}"#)
+        .unwrap()
+        .with_namespace("app");
+    kv.set("a", 1).unwrap();
+    kv.set("b", 2).unwrap();
+    kv.set("name", "alice").unwrap();
+
+    let mut numbers: Vec<(String, i32)> = kv.iter().collect();
+    numbers.sort();
+    assert_eq!(numbers, vec![("a".to_string(), 1), ("b".to_string(), 2)]);
+
+    let results: Vec<_> = kv.try_iter::<i32>().collect();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results.iter().filter(|result| result.is_err()).count(), 1);
+    assert_eq!(kv.raw_len(), 4);
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_values() {
+    let mut kv = TinyKV::new().with_namespace("users");
+    kv.set("alice", 30).unwrap();
+    kv.set("bob", 25).unwrap();
+    kv.set_in("other", "carol", "not a number").unwrap();
+
+    let mut ages: Vec<i32> = kv.values().unwrap();
+    ages.sort();
+    assert_eq!(ages, vec![25, 30]);
+
+    kv.set("dave", "unknown").unwrap();
+    assert!(kv.values::<i32>().is_err());
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_with_codec() {
+    use crate::CompactJsonCodec;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("compact.json");
+    let mut kv = TinyKV::open(&path).unwrap().with_codec(CompactJsonCodec);
+    kv.set("a", 1).unwrap();
+    kv.set("b", vec![1, 2]).unwrap();
+    kv.save().unwrap();
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    assert!(!contents.contains('\n'));
+    assert!(!contents.contains(' '));
+
+    let mut reopened = TinyKV::new().with_codec(CompactJsonCodec).load_from(&path).unwrap();
+    assert_eq!(reopened.get::<Vec<i32>>("b").unwrap(), Some(vec![1, 2]));
+    assert!(TinyKV::open(&path).unwrap().contains_key("a"));
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_with_pretty() {
+    let mut kv = TinyKV::new();
+    for i in 0..20 {
+        kv.set(&format!("key{i}"), i).unwrap();
+    }
+
+    let pretty = kv.to_data().unwrap();
+    let kv = kv.with_pretty(false);
+    let compact = kv.to_data().unwrap();
+    assert!(compact.len() < pretty.len());
+    assert_eq!(TinyKV::from_data(&compact).unwrap().len(), 20);
+    assert_eq!(kv.with_pretty(true).to_data().unwrap().len(), pretty.len());
+
+    let custom = TinyKV::from_data(&compact).unwrap().with_codec(crate::CompactJsonCodec);
+    assert_eq!(custom.with_pretty(true).to_data().unwrap().len(), compact.len());
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "compression"))]
+#[test]
+fn test_with_compression() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json.gz");
+    let mut kv = TinyKV::open(&path).unwrap().with_compression(true);
+    kv.set("text", "a".repeat(1000)).unwrap();
+    kv.save().unwrap();
+
+    let bytes = std::fs::read(&path).unwrap();
+    assert!(bytes.starts_with(&[0x1f, 0x8b]));
+    assert!(bytes.len() < 1000);
+    assert!(!dir.path().join("store.json.tmp").exists());
+
+    let mut reopened = TinyKV::open(&path).unwrap();
+    assert_eq!(reopened.get::<String>("text").unwrap(), Some("a".repeat(1000)));
+
+    let kv = reopened.with_compression(false);
+    kv.save().unwrap();
+    assert!(std::fs::read_to_string(&path).unwrap().contains("\"text\""));
+}
+
+#[cfg(all(feature = "std", not(feature = "compression")))]
+#[test]
+fn test_gzipped_file_without_compression() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json.gz");
+    std::fs::write(&path, [0x1f, 0x8b, 0x08, 0x00]).unwrap();
+
+    let Err(err) = TinyKV::open(&path) else {
+        panic!("expected a gzipped store to fail to open");
+    };
+    assert!(err.to_string().contains("`compression` feature"));
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "encryption"))]
+#[test]
+fn test_encryption_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("secrets.json");
+    let mut kv = TinyKV::new().with_encryption_key([7; 32]).load_from(&path).unwrap();
+    kv.set("api_token", "s3cr3t").unwrap();
+    kv.save().unwrap();
+
+    let bytes = std::fs::read(&path).unwrap();
+    assert!(!String::from_utf8_lossy(&bytes).contains("s3cr3t"));
+    assert!(TinyKV::open(&path).is_err());
+
+    let mut reopened = TinyKV::new().with_encryption_key([7; 32]).load_from(&path).unwrap();
+    assert_eq!(reopened.get::<String>("api_token").unwrap().unwrap(), "s3cr3t");
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "encryption"))]
+#[test]
+fn test_encryption_wrong_key() {
+    use crate::TinyKVError;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("secrets.json");
+    let mut kv = TinyKV::new().with_encryption_key([7; 32]).load_from(&path).unwrap();
+    kv.set("api_token", "s3cr3t").unwrap();
+    kv.save().unwrap();
+
+    let err = TinyKV::new().with_encryption_key([8; 32]).load_from(&path).err().unwrap();
+    assert!(matches!(err, TinyKVError::Decryption));
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_get_required() {
+    use crate::TinyKVError;
+
+    let mut kv = TinyKV::from_data(r#"{"app:old": {"value": 0, "expires_at": 1}}"#)
+        .unwrap()
+        .with_namespace("app");
+    kv.set("port", 8080).unwrap();
+
+    assert_eq!(kv.get_required::<u16>("port").unwrap(), 8080);
+    assert!(matches!(
+        kv.get_required::<u16>("old"),
+        Err(TinyKVError::KeyNotFound(key)) if key == "old"
+    ));
+    assert!(matches!(kv.get_required::<u16>("missing"), Err(TinyKVError::KeyNotFound(_))));
+    assert!(matches!(kv.get_required::<String>("port"), Err(TinyKVError::Serialization(_))));
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_peek() {
+    use std::sync::RwLock;
+
+    let mut kv = TinyKV::from_data(r#"{"app:old": {"value": 0, "expires_at": 1}}"#)
+        .unwrap()
+        .with_namespace("app");
+    kv.set("port", 8080).unwrap();
+
+    let shared = RwLock::new(kv);
+    let kv = shared.read().unwrap();
+    assert_eq!(kv.peek::<u16>("port").unwrap(), Some(8080));
+    assert_eq!(kv.peek::<u16>("old").unwrap(), None);
+    assert_eq!(kv.peek::<u16>("missing").unwrap(), None);
+    assert_eq!(kv.raw_len(), 2);
+    assert_eq!(kv.entry_metadata("port").unwrap().hits, 0);
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "file-lock"))]
+#[test]
+fn test_file_lock_serializes_saves() {
+    use crate::TinyKVError;
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("shared.json");
+    let log_path = dir.path().join("events.log");
+    let timeout = Duration::from_millis(50);
+
+    let mut kv = TinyKV::open(&path).unwrap().with_lock_timeout(timeout);
+    kv.set("writer", 1).unwrap();
+    kv.save().unwrap();
+    let mut log = TinyKV::open_append_log(&log_path).unwrap().with_lock_timeout(timeout);
+
+    let holder = TinyKV::open_locked(&path).unwrap();
+    let log_holder = TinyKV::open_locked(&log_path).unwrap();
+    kv.set("writer", 2).unwrap();
+    assert!(matches!(kv.save(), Err(TinyKVError::Locked)));
+    assert!(matches!(kv.checkpoint(), Err(TinyKVError::Locked)));
+    assert!(matches!(log.set("writer", 2), Err(TinyKVError::Locked)));
+    assert!(std::fs::read_to_string(&log_path).unwrap_or_default().is_empty());
+
+    drop(holder);
+    drop(log_holder);
+    kv.save().unwrap();
+    log.set("writer", 3).unwrap();
+    let writer: i32 = TinyKV::open(&path).unwrap().get("writer").unwrap().unwrap();
+    assert_eq!(writer, 2);
+    let logged: i32 = TinyKV::open_append_log(&log_path).unwrap().get("writer").unwrap().unwrap();
+    assert_eq!(logged, 3);
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "file-lock"))]
+#[test]
+fn test_open_locked_reports_contention() {
+    use crate::TinyKVError;
+    use std::time::Duration;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("shared.json");
+    let mut owner = TinyKV::open_locked(&path).unwrap();
+    owner.set("a", 1).unwrap();
+    owner.save().unwrap();
+
+    let path_clone = path.clone();
+    let err = std::thread::spawn(move || {
+        let mut other = TinyKV::open(&path_clone)
+            .unwrap()
+            .with_lock_timeout(Duration::from_millis(50));
+        other.set("b", 2).unwrap();
+        other.save().err()
+    })
+    .join()
+    .unwrap();
+    assert!(matches!(err, Some(TinyKVError::Locked)));
+
+    drop(owner);
+    let mut other = TinyKV::open(&path).unwrap();
+    other.set("b", 2).unwrap();
+    other.save().unwrap();
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_on_change() {
+    use crate::ChangeKind;
+    use std::sync::{Arc, Mutex};
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&events);
+    let mut kv = TinyKV::from_data(r#"{"app:old": {"value": 0, "expires_at": 1}}"#)
+        .unwrap()
+        .with_namespace("app");
+    kv.on_change(move |key, kind| recorded.lock().unwrap().push((key.to_string(), kind)));
+
+    kv.set("a", 1).unwrap();
+    kv.set_with_ttl("b", 2, 60).unwrap();
+    assert!(kv.remove("a").unwrap());
+    assert!(!kv.remove("a").unwrap());
+    let _: Option<i32> = kv.get("old").unwrap();
+    kv.clear().unwrap();
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            ("a".to_string(), ChangeKind::Set),
+            ("b".to_string(), ChangeKind::Set),
+            ("a".to_string(), ChangeKind::Removed),
+            ("old".to_string(), ChangeKind::Expired),
+            (String::new(), ChangeKind::Cleared),
+        ]
+    );
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_snapshot_restore() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("snap.json");
+    let mut kv = TinyKV::open(&path).unwrap().with_auto_save();
+    kv.set("a", 1).unwrap();
+    kv.set("b", 2).unwrap();
+
+    let snap = kv.snapshot();
+    kv.remove("a").unwrap();
+    kv.set("b", 20).unwrap();
+    kv.set("c", 3).unwrap();
+
+    kv.restore(snap.clone()).unwrap();
+    assert_eq!(kv.get::<i32>("a").unwrap(), Some(1));
+    assert_eq!(kv.get::<i32>("b").unwrap(), Some(2));
+    assert!(!kv.contains_key("c"));
+
+    let reopened = TinyKV::open(&path).unwrap();
+    assert!(reopened.contains_key("a"));
+    assert!(!reopened.contains_key("c"));
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_transaction() {
+    use crate::{Op, TinyKVError};
+    use std::sync::{Arc, Mutex};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tx.json");
+    let saves = Arc::new(Mutex::new(0));
+    let counter = Arc::clone(&saves);
+    let mut kv = TinyKV::open(&path)
+        .unwrap()
+        .with_auto_save()
+        .with_observer(Box::new(move |op, _| {
+            if op == Op::Save {
+                *counter.lock().unwrap() += 1;
+            }
+        }));
+    kv.set("checking", 100).unwrap();
+    kv.set("savings", 0).unwrap();
+    *saves.lock().unwrap() = 0;
+
+    let moved = kv
+        .transaction(|kv| {
+            kv.increment("checking", -40)?;
+            kv.increment("savings", 40)
+        })
+        .unwrap();
+    assert_eq!(moved, 40);
+    assert_eq!(*saves.lock().unwrap(), 1);
+
+    let err = kv
+        .transaction(|kv| {
+            kv.increment("checking", -100)?;
+            kv.remove("savings")?;
+            Err::<(), _>(TinyKVError::Serialization("insufficient funds".to_string()))
+        })
+        .unwrap_err();
+    assert!(err.to_string().contains("insufficient funds"));
+    assert_eq!(*saves.lock().unwrap(), 1);
+    assert_eq!(kv.get::<i64>("checking").unwrap(), Some(60));
+    assert_eq!(kv.get::<i64>("savings").unwrap(), Some(40));
+
+    let reopened = TinyKV::open(&path).unwrap();
+    assert_eq!(reopened.peek::<i64>("checking").unwrap(), Some(60));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_transaction_in_append_log() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.log");
+    let mut log = TinyKV::open_append_log(&path).unwrap();
+    log.set("a", "1".to_string()).unwrap();
+
+    log.transaction(|kv| {
+        kv.set("b", "2".to_string())?;
+        kv.save()?;
+        kv.set("c", "3".to_string())
+    })
+    .unwrap();
+    assert_eq!(TinyKV::open_append_log(&path).unwrap().len(), 3);
+
+    let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        log.transaction(|kv| -> Result<(), crate::TinyKVError> {
+            kv.set("d", "4".to_string())?;
+            panic!("closure failed");
+        })
+    }));
+    assert!(panicked.is_err());
+
+    // Appends resume once the transaction is unwound.
+    log.set("e", "5".to_string()).unwrap();
+    assert!(TinyKV::open_append_log(&path).unwrap().contains_key("e"));
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_rename_key() {
+    use crate::Ttl;
+
+    let mut kv = TinyKV::from_data(r#"{"app:old": {"value": 0, "expires_at": 1}}"#)
+        .unwrap()
+        .with_namespace("app");
+    kv.set_with_ttl("config_v1", "blue", 60).unwrap();
+    kv.set("config_v2", "stale").unwrap();
+
+    assert!(kv.rename_key("config_v1", "config_v2").unwrap());
+    assert!(!kv.contains_key("config_v1"));
+    assert_eq!(kv.get::<String>("config_v2").unwrap().unwrap(), "blue");
+    assert!(matches!(kv.get_ttl("config_v2").unwrap(), Some(Ttl::Expires(59..=60))));
+
+    assert!(kv.rename_key("config_v2", "config_v2").unwrap());
+    assert!(kv.contains_key("config_v2"));
+    assert!(!kv.rename_key("missing", "other").unwrap());
+    assert!(!kv.rename_key("old", "revived").unwrap());
+    assert!(!kv.contains_key("revived"));
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_backup_rotation() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+    let mut kv = TinyKV::open(&path)
+        .unwrap()
+        .with_backup(true)
+        .with_backup_rotation(3);
+
+    for version in 0..5 {
+        kv.set("version", version).unwrap();
+        kv.save().unwrap();
+    }
+
+    let mut backups: Vec<String> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.contains(".bak"))
+        .collect();
+    backups.sort();
+    assert_eq!(backups, vec!["store.bak.1", "store.bak.2", "store.bak.3"]);
+
+    for (n, version) in [(1, 3), (2, 2), (3, 1)] {
+        let backup = TinyKV::open(dir.path().join(format!("store.bak.{n}"))).unwrap();
+        assert_eq!(backup.peek::<i32>("version").unwrap(), Some(version));
+    }
+
+    assert!(kv.restore_from_backup().unwrap());
+    assert_eq!(kv.get::<i32>("version").unwrap(), Some(3));
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_timestamped_backups() {
+    use crate::BackupStrategy;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+    let mut kv = TinyKV::open(&path)
+        .unwrap()
+        .with_backup(true)
+        .with_backup_strategy(BackupStrategy::Timestamped);
+    kv.set("version", 1).unwrap();
+    kv.save().unwrap();
+    kv.set("version", 2).unwrap();
+    kv.save().unwrap();
+
+    // 1_000_000_000 seconds after the epoch
+    let old = dir.path().join("store.2001-09-09T01-46-40.json.bak");
+    std::fs::copy(&path, &old).unwrap();
+    let unrelated = dir.path().join("store.latest.json.bak");
+    std::fs::copy(&path, &unrelated).unwrap();
+
+    let backups: Vec<String> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .filter(|name| name.starts_with("store.20") && name.ends_with(".json.bak"))
+        .collect();
+    assert_eq!(backups.len(), 2);
+
+    assert_eq!(kv.prune_backups(3600).unwrap(), 1);
+    assert!(!old.exists());
+    assert!(unrelated.exists());
+
+    kv.set("version", 3).unwrap();
+    assert!(kv.restore_from_backup().unwrap());
+    assert_eq!(kv.get::<i32>("version").unwrap(), Some(1));
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_open_read_only() {
+    use crate::TinyKVError;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+    std::fs::write(
+        &path,
+        r#"{"name": {"value": "tiny"}, "old": {"value": 0, "expires_at": 1}}"#,
+    )
+    .unwrap();
+
+    let mut kv = TinyKV::open_read_only(&path).unwrap().with_auto_save();
+    assert_eq!(kv.get::<String>("name").unwrap(), Some("tiny".to_string()));
+    assert_eq!(kv.get::<i32>("old").unwrap(), None);
+    assert_eq!(kv.raw_len(), 2);
+
+    assert!(matches!(kv.set("name", "other"), Err(TinyKVError::ReadOnly)));
+    assert!(matches!(kv.remove("name"), Err(TinyKVError::ReadOnly)));
+    assert!(matches!(kv.clear(), Err(TinyKVError::ReadOnly)));
+    assert!(matches!(kv.save(), Err(TinyKVError::ReadOnly)));
+    assert!(matches!(kv.prune_checkpoints(0), Err(TinyKVError::ReadOnly)));
+    assert!(matches!(kv.prune_backups(0), Err(TinyKVError::ReadOnly)));
+    assert_eq!(kv.get::<String>("name").unwrap(), Some("tiny".to_string()));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_all_keys_and_namespaces() {
+    let mut kv = TinyKV::from_data(
+        r#"{"app2:x": {"value": "1"}, "app1:user:a": {"value": "1"},
+            "gone:y": {"value": "0", "expires_at": 1}, "plain": {"value": "1"}}"#,
+    )
+    .unwrap()
+    .with_namespace("app1");
+    kv.set("b", "1".to_string()).unwrap();
+
+    assert_eq!(kv.keys().len(), 2);
+    assert_eq!(kv.all_keys(), vec!["app1:b", "app1:user:a", "app2:x", "plain"]);
+    assert_eq!(kv.namespaces(), vec!["app1", "app2"]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_sub_namespace_view() {
+    let mut kv = TinyKV::new().with_namespace("app1");
+    kv.set("top", 1).unwrap();
+
+    let mut user = kv.sub("user");
+    assert_eq!(user.namespace(), "app1:user:");
+    user.set("name", "alice".to_string()).unwrap();
+    user.set_with_ttl("session", 7, 60).unwrap();
+    let mut prefs = user.sub("prefs:");
+    assert_eq!(prefs.namespace(), "app1:user:prefs:");
+    prefs.set("theme", "dark".to_string()).unwrap();
+    assert!(!prefs.contains_key("name"));
+
+    let mut keys = user.keys();
+    keys.sort();
+    assert_eq!(keys, vec!["name", "prefs:theme", "session"]);
+    assert_eq!(user.get::<String>("name").unwrap(), Some("alice".to_string()));
+    assert!(user.remove("session").unwrap());
+
+    assert!(kv.contains_key("user:name"));
+    assert!(kv.contains_key("top"));
+    assert_eq!(kv.get::<String>("user:prefs:theme").unwrap(), Some("dark".to_string()));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_typed_entry() {
+    let mut kv = TinyKV::new().with_namespace("app");
+    let mut name = kv.entry::<String>("name");
+    assert!(!name.exists());
+    assert_eq!(name.get().unwrap(), None);
+
+    name.set("tiny".to_string()).unwrap();
+    assert_eq!(name.get().unwrap(), Some("tiny".to_string()));
+    name.set_with_ttl("kv".to_string(), 60).unwrap();
+    assert!(name.exists());
+    assert!(kv.get_ttl("name").unwrap().is_some());
+
+    let mut name = kv.entry::<String>("name");
+    assert!(name.remove().unwrap());
+    assert!(!name.exists());
+    assert!(!kv.contains_key("name"));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_purge_expired_into() {
+    use crate::Op;
+    use std::sync::{Arc, Mutex};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("purge.json");
+    std::fs::write(
+        &path,
+        r#"{"app:b": {"value": "0", "expires_at": 1}, "app:a": {"value": "0", "expires_at": 1},
+            "app:live": {"value": "1"}}"#,
+    )
+    .unwrap();
+    let saves = Arc::new(Mutex::new(0));
+    let counter = Arc::clone(&saves);
+    let mut kv = TinyKV::open(&path)
+        .unwrap()
+        .with_namespace("app")
+        .with_auto_save()
+        .with_observer(Box::new(move |op, _| {
+            if op == Op::Save {
+                *counter.lock().unwrap() += 1;
+            }
+        }));
+
+    assert_eq!(kv.purge_expired_into().unwrap(), vec!["a".to_string(), "b".to_string()]);
+    assert_eq!(*saves.lock().unwrap(), 1);
+    assert_eq!(kv.raw_len(), 1);
+
+    assert!(kv.purge_expired_into().unwrap().is_empty());
+    assert_eq!(kv.purge_expired().unwrap(), 0);
+    assert_eq!(*saves.lock().unwrap(), 1);
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_with_migration() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+    std::fs::write(&path, r#"{"name": {"value": "alice"}}"#).unwrap();
+
+    let migrate = |from: u32, data: &mut std::collections::HashMap<String, crate::Entry>| {
+        assert_eq!(from, 0);
+        for entry in data.values_mut() {
+            entry.value = serde_json::json!({ "first": entry.value.take() });
+        }
+        Ok(())
+    };
+    let mut kv = TinyKV::open(&path).unwrap().with_migration(2, migrate).unwrap();
+    assert_eq!(kv.keys(), vec!["name".to_string()]);
+    assert_eq!(kv.all_keys(), vec!["name".to_string()]);
+    assert_eq!(kv.len(), 1);
+    assert_eq!(kv.raw_len(), 2);
+    let name: serde_json::Value = kv.get("name").unwrap().unwrap();
+    assert_eq!(name["first"], "alice");
+
+    let kv = TinyKV::open(&path)
+        .unwrap()
+        .with_migration(2, |_, _| panic!("already migrated"))
+        .unwrap();
+    assert_eq!(kv.len(), 1);
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_version_stamp_is_reserved() {
+    use crate::TinyKVError;
+
+    let mut kv = TinyKV::new()
+        .with_capacity(1)
+        .with_migration(1, |_, _| Ok(()))
+        .unwrap();
+    let err = kv.set("__tinykv:version", 5).unwrap_err();
+    assert!(matches!(err, TinyKVError::ReservedKey(ref key) if key == "__tinykv:version"));
+    assert!(kv.increment("__tinykv:version", 1).is_err());
+
+    kv.set("a", 1).unwrap();
+    kv.set("b", 2).unwrap();
+    assert!(!kv.contains_key("a"));
+    assert_eq!(kv.for_each_mut(|_, _| true).unwrap(), 1);
+
+    kv.clear_prefix("").unwrap();
+    kv.set("c", 3).unwrap();
+    kv.clear().unwrap();
+    kv.restore(TinyKV::new().snapshot()).unwrap();
+    assert_eq!(kv.len(), 0);
+    assert_eq!(kv.raw_len(), 1);
+    assert!(kv.to_data().unwrap().contains("__tinykv:version"));
+    assert!(kv.is_empty());
+    assert!(kv.all_expiries().is_empty());
+    assert!(kv.keys_modified_after(0).is_empty());
+    assert_eq!(kv.gc_stats().total_entries, 0);
+
+    assert!(!kv.rename_key("__tinykv:version", "v").unwrap());
+    kv.set("xversion", 1).unwrap();
+    assert!(matches!(
+        kv.rename_key("xversion", "__tinykv:version"),
+        Err(TinyKVError::ReservedKey(_))
+    ));
+    assert!(matches!(
+        kv.rename_prefix("x", "__tinykv:"),
+        Err(TinyKVError::ReservedKey(_))
+    ));
+    assert!(kv.contains_key("xversion"));
+
+    kv.reset_to_data("{}").unwrap();
+    assert_eq!(kv.raw_len(), 1);
+    assert!(kv.to_data().unwrap().contains("__tinykv:version"));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_purge_on_load() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+    let contents = r#"{"live": {"value": "1"}, "old": {"value": "0", "expires_at": 1}}"#;
+    std::fs::write(&path, contents).unwrap();
+
+    let mut kv = TinyKV::open(&path).unwrap().with_purge_on_load(true);
+    assert_eq!(kv.raw_len(), 1);
+    assert!(kv.contains_key("live"));
+
+    std::fs::write(&path, contents).unwrap();
+    kv.reload().unwrap();
+    assert_eq!(kv.raw_len(), 1);
+
+    let kv = TinyKV::open(&path).unwrap().with_purge_on_load(false);
+    assert_eq!(kv.raw_len(), 2);
+
+    // Append-log stores record the purge as tombstones.
+    let log_path = dir.path().join("events.log");
+    std::fs::write(&log_path, contents.to_string() + "\n").unwrap();
+    let kv = TinyKV::open_append_log(&log_path).unwrap().with_purge_on_load(true);
+    assert_eq!(kv.raw_len(), 1);
+    let log = std::fs::read_to_string(&log_path).unwrap();
+    assert!(log.lines().last().unwrap().contains(r#""deleted":true"#));
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_merge_file() {
+    use crate::MergeStrategy;
+
+    let dir = tempfile::tempdir().unwrap();
+    let other_path = dir.path().join("other.json");
+    std::fs::write(
+        &other_path,
+        r#"{
+            "shared": {"value": "theirs", "expires_at": null, "modified_at": 1},
+            "new": {"value": "added", "expires_at": null},
+            "gone": {"value": "stale", "expires_at": 1}
+        }"#,
+    )
+    .unwrap();
+
+    let mut kv = TinyKV::new();
+    kv.set("shared", "ours").unwrap();
+    assert_eq!(kv.merge_file(&other_path, MergeStrategy::Newest).unwrap(), 1);
+    assert_eq!(kv.get::<String>("shared").unwrap().unwrap(), "ours");
+    assert_eq!(kv.get::<String>("new").unwrap().unwrap(), "added");
+    assert!(!kv.contains_key("gone"));
+
+    assert_eq!(kv.merge_file(&other_path, MergeStrategy::KeepExisting).unwrap(), 0);
+    assert_eq!(kv.merge_file(&other_path, MergeStrategy::Overwrite).unwrap(), 2);
+    assert_eq!(kv.get::<String>("shared").unwrap().unwrap(), "theirs");
+
+    assert!(kv.merge_file(dir.path().join("missing.json"), MergeStrategy::Newest).is_err());
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_set_null() {
+    let mut kv = TinyKV::new();
+    kv.set_null("flag").unwrap();
+
+    assert!(kv.contains_key("flag"));
+    assert_eq!(kv.get::<serde_json::Value>("flag").unwrap(), Some(serde_json::Value::Null));
+    assert_eq!(kv.get::<Option<bool>>("flag").unwrap(), Some(None));
+    assert_eq!(kv.get::<serde_json::Value>("missing").unwrap(), None);
+
+    let restored = TinyKV::from_data(&kv.to_data().unwrap()).unwrap();
+    assert!(restored.contains_key("flag"));
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_remove_prefix_returning() {
+    let stale = r#"{"app:job:old": {"value": "stale", "expires_at": 1}}"#;
+    let mut kv = TinyKV::from_data(stale).unwrap().with_namespace("app");
+    kv.set("job:2", "b").unwrap();
+    kv.set("job:1", "a").unwrap();
+    kv.set("other", "c").unwrap();
+
+    let removed: Vec<(String, String)> = kv.remove_prefix_returning("job:").unwrap();
+    assert_eq!(
+        removed,
+        vec![("job:1".to_string(), "a".to_string()), ("job:2".to_string(), "b".to_string())]
+    );
+    assert_eq!(kv.raw_len(), 1);
+    assert!(kv.contains_key("other"));
+
+    kv.set("num", 1).unwrap();
+    assert!(kv.remove_prefix_returning::<String>("num").is_err());
+    assert!(kv.contains_key("num"));
+}
+
+#[cfg(all(not(feature = "nanoserde"), feature = "std"))]
+#[test]
+fn test_observer() {
+    use crate::Op;
+    use std::sync::{Arc, Mutex};
+
+    let dir = tempfile::tempdir().unwrap();
+    let ops = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&ops);
+    let mut kv = TinyKV::open(dir.path().join("observed.json"))
+        .unwrap()
+        .with_observer(Box::new(move |op, _elapsed| recorded.lock().unwrap().push(op)));
+
+    kv.set("a", 1).unwrap();
+    let _: Option<i32> = kv.get("a").unwrap();
+    kv.save().unwrap();
+    kv.reload().unwrap();
+
+    assert_eq!(
+        *ops.lock().unwrap(),
+        vec![Op::Set, Op::Get, Op::Serialize, Op::Save, Op::Deserialize, Op::Load]
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_open_rejects_non_object_root() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("list.json");
+    std::fs::write(&path, "42").unwrap();
+
+    let err = TinyKV::open(&path).err().unwrap();
+    assert!(
+        err.to_string()
+            .contains("expected a TinyKV object map or array of pairs, found number")
+    );
+}
+
+#[cfg(all(feature = "std", not(feature = "nanoserde")))]
+#[test]
+fn test_strict_rejects_duplicate_keys() {
+    let data = r#"{"name": {"value": "alice"}, "name": {"value": "bob"}}"#;
+
+    let mut lenient = TinyKV::from_data(data).unwrap();
+    let name: String = lenient.get("name").unwrap().unwrap();
+    assert_eq!(name, "bob");
+
+    let err = TinyKV::from_data_strict(data).err().unwrap();
+    assert!(err.to_string().contains("duplicate key `name`"));
+
+    let pairs = r#"[["id", {"value": 1}], ["id", {"value": 2}]]"#;
+    assert!(TinyKV::from_data_strict(pairs).is_err());
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+    std::fs::write(&path, data).unwrap();
+    assert!(TinyKV::open_strict(&path).is_err());
+    std::fs::write(&path, r#"{"name": {"value": "alice"}}"#).unwrap();
+    assert_eq!(TinyKV::open_strict(&path).unwrap().keys(), vec!["name".to_string()]);
+
+    std::fs::write(&path, format!("# tinykv\n{data}")).unwrap();
+    let strip_header =
+        || Box::new(|contents: String| contents.trim_start_matches("# tinykv\n").to_string());
+    assert!(TinyKV::new().with_post_load(strip_header()).load_from(&path).is_ok());
+    let strict = TinyKV::new().with_strict_load().with_post_load(strip_header()).load_from(&path);
+    assert!(strict.err().unwrap().to_string().contains("duplicate key `name`"));
+}
+
+#[cfg(all(feature = "std", not(feature = "nanoserde")))]
+#[test]
+fn test_open_accepts_pair_array() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("pairs.json");
+    std::fs::write(
+        &path,
+        r#"  [["name", {"value": "alice", "expires_at": null}], ["count", {"value": 3, "expires_at": null}]]"#,
+    )
+    .unwrap();
+
+    let mut kv = TinyKV::open(&path).unwrap();
+    let name: String = kv.get("name").unwrap().unwrap();
+    let count: i32 = kv.get("count").unwrap().unwrap();
+    assert_eq!((name.as_str(), count), ("alice", 3));
+
+    assert!(TinyKV::from_data("[1, 2, 3]").is_err());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_rename_prefix() {
+    let temp_file = tempfile::NamedTempFile::new().unwrap();
+    let mut kv = TinyKV::open(temp_file.path()).unwrap().with_auto_save();
+    kv.set_with_ttl("user:1", "alice".to_string(), 3600).unwrap();
+    kv.set("user:2", "bob".to_string()).unwrap();
+    kv.set("account:2", "stale".to_string()).unwrap();
+    kv.set("other", "x".to_string()).unwrap();
+
+    assert_eq!(kv.rename_prefix("user:", "account:").unwrap(), 2);
+    assert!(!kv.contains_key("user:1"));
+
+    let mut reopened = TinyKV::open(temp_file.path()).unwrap();
+    let bob: String = reopened.get("account:2").unwrap().unwrap();
+    assert_eq!(bob, "bob");
+    assert!(reopened.entry_metadata("account:1").unwrap().expires_at.is_some());
+    assert_eq!(reopened.keys().len(), 3);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_soft_limit() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let fired = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&fired);
+    let mut kv = TinyKV::new().with_soft_limit(
+        2,
+        Box::new(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }),
+    );
+
+    for i in 0..5 {
+        kv.set(&format!("key{i}"), i).unwrap();
+    }
+    assert_eq!(fired.load(Ordering::SeqCst), 1);
+
+    // Dropping back under the limit re-arms the callback.
+    kv.clear_memory();
+    for i in 0..3 {
+        kv.set(&format!("key{i}"), i).unwrap();
+    }
+    assert_eq!(fired.load(Ordering::SeqCst), 2);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_to_data_filtered() {
+    let mut kv = TinyKV::new();
+    kv.set("tenant1:name", "alice".to_string()).unwrap();
+    kv.set("tenant2:name", "bob".to_string()).unwrap();
+
+    let data = kv.to_data_filtered(|key| key.starts_with("tenant1:")).unwrap();
+    let mut restored = TinyKV::from_data(&data).unwrap();
+    assert_eq!(restored.keys(), vec!["tenant1:name".to_string()]);
+    let name: String = restored.get("tenant1:name").unwrap().unwrap();
+    assert_eq!(name, "alice");
+
+    // The version stamp is never exported, and the store's codec is used.
+    let kv = TinyKV::new().with_migration(1, |_, _| Ok(())).unwrap();
+    #[cfg(not(feature = "nanoserde"))]
+    let kv = kv.with_pretty(false);
+    let mut kv = kv;
+    kv.set("key", 1).unwrap();
+    let data = kv.to_data_filtered(|_| true).unwrap();
+    assert!(!data.contains("__tinykv:version"));
+    #[cfg(not(feature = "nanoserde"))]
+    assert!(!data.contains('\n'));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_entry_metadata() {
+    let mut kv = TinyKV::new();
+    kv.set("key", 1).unwrap();
+    let created = kv.entry_metadata("key").unwrap();
+    assert!(created.created_at.is_some());
+    assert_eq!(created.hits, 0);
+    assert_eq!(created.accessed_at, None);
+
+    let _: Option<i32> = kv.get("key").unwrap();
+    let _: Option<i32> = kv.get("key").unwrap();
+    kv.set("key", 2).unwrap();
+
+    // Overwriting keeps the creation time and read statistics.
+    let meta = kv.entry_metadata("key").unwrap();
+    assert_eq!(meta.hits, 2);
+    assert!(meta.accessed_at.is_some());
+    assert_eq!(meta.created_at, created.created_at);
+    assert!(kv.entry_metadata("missing").is_none());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_lfu_eviction() {
+    use crate::EvictionPolicy;
+
+    let mut kv = TinyKV::new()
+        .with_capacity(2)
+        .with_eviction(EvictionPolicy::Lfu);
+    kv.set("hot", 1).unwrap();
+    kv.set("cold", 2).unwrap();
+    for _ in 0..3 {
+        let _: Option<i32> = kv.get("hot").unwrap();
+    }
+
+    kv.set("new", 3).unwrap();
+    assert!(kv.contains_key("hot"));
+    assert!(!kv.contains_key("cold"));
+    assert!(kv.contains_key("new"));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_lru_eviction() {
+    let mut kv = TinyKV::new().with_capacity(2);
+    kv.set("a", "1".to_string()).unwrap();
+    kv.set("b", "2".to_string()).unwrap();
+    let _: Option<String> = kv.get("a").unwrap();
+
+    // All three writes land within the same second; recency still orders them.
+    kv.set("c", "3".to_string()).unwrap();
+    assert!(kv.contains_key("a"));
+    assert!(!kv.contains_key("b"));
+    assert!(kv.contains_key("c"));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_fifo_eviction() {
+    use crate::EvictionPolicy;
+
+    let mut kv = TinyKV::new()
+        .with_capacity(2)
+        .with_eviction(EvictionPolicy::Fifo);
+    kv.set("a", "1".to_string()).unwrap();
+    kv.set("b", "2".to_string()).unwrap();
+    let _: Option<String> = kv.get("a").unwrap();
+    kv.set("a", "updated".to_string()).unwrap();
+
+    // Reads and overwrites don't move `a` out of first place.
+    kv.set("c", "3".to_string()).unwrap();
+    assert!(!kv.contains_key("a"));
+    assert!(kv.contains_key("b"));
+    assert!(kv.contains_key("c"));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_on_evict() {
+    use std::sync::{Arc, Mutex};
+
+    let evicted = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&evicted);
+    let mut kv = TinyKV::new().with_namespace("app").with_capacity(2);
+    assert_eq!(kv.capacity(), Some(2));
+    assert_eq!(TinyKV::new().capacity(), None);
+    kv.on_evict(move |key| recorded.lock().unwrap().push(key));
+
+    kv.set("a", "1".to_string()).unwrap();
+    kv.set("b", "2".to_string()).unwrap();
+    assert!(evicted.lock().unwrap().is_empty());
+    kv.set("c", "3".to_string()).unwrap();
+
+    assert_eq!(*evicted.lock().unwrap(), vec!["a".to_string()]);
+    assert_eq!(kv.raw_len(), 2);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_reset_to_data() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("store.json");
+
+    let mut fixture = TinyKV::new();
+    fixture.set("seed", "fixture".to_string()).unwrap();
+    let fixture = fixture.to_data().unwrap();
+
+    let mut kv = TinyKV::open(&path).unwrap();
+    kv.set("leftover", 1).unwrap();
+    kv.reset_to_data(&fixture).unwrap();
+    assert_eq!(kv.keys(), vec!["seed".to_string()]);
+
+    let mut reopened = TinyKV::open(&path).unwrap();
+    let seed: String = reopened.get("seed").unwrap().unwrap();
+    assert_eq!(seed, "fixture");
+
+    assert!(kv.reset_to_data("42").is_err());
+    assert_eq!(kv.keys(), vec!["seed".to_string()]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_keys_with_value() {
+    let mut kv = TinyKV::new();
+    kv.set("session1", 42).unwrap();
+    kv.set("session2", 7).unwrap();
+    kv.set("session3", 42).unwrap();
+
+    let mut keys = kv.keys_with_value(&42).unwrap();
+    keys.sort();
+    assert_eq!(keys, vec!["session1".to_string(), "session3".to_string()]);
+    assert!(kv.keys_with_value(&99).unwrap().is_empty());
+}
+
+#[cfg(all(feature = "logging", feature = "std", not(feature = "nanoserde")))]
+#[test]
+fn test_logging_records_and_redaction() {
+    use std::sync::Mutex;
+
+    static RECORDS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    struct Capture;
+    impl log::Log for Capture {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+        fn log(&self, record: &log::Record) {
+            RECORDS.lock().unwrap().push(record.args().to_string());
+        }
+        fn flush(&self) {}
+    }
+
+    static LOGGER: Capture = Capture;
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+
+    let mut kv = TinyKV::new();
+    kv.set("visible_log_key", 1).unwrap();
+    assert!(kv.get::<i32>("missing_log_key").unwrap().is_none());
+
+    let mut redacted = TinyKV::new().with_redacted_log_keys();
+    redacted.set("secret_log_key", 1).unwrap();
+    assert!(redacted.get::<i32>("secret_missing_key").unwrap().is_none());
+
+    let records = RECORDS.lock().unwrap();
+    assert!(records.iter().any(|r| r == "tinykv: set visible_log_key"));
+    assert!(records.iter().any(|r| r == "tinykv: get miss missing_log_key"));
+    assert!(records.iter().any(|r| r == "tinykv: set <redacted>"));
+    assert!(records.iter().any(|r| r == "tinykv: get miss <redacted>"));
+    assert!(!records.iter().any(|r| r.contains("secret_")));
+}
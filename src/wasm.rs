@@ -17,6 +17,16 @@ extern "C" {
     #[wasm_bindgen(js_namespace = localStorage, js_name = removeItem)]
     pub fn ls_remove_item(key: &str);
 
+    // Throwing variants, used to probe whether localStorage is usable at all
+    #[wasm_bindgen(catch, js_namespace = localStorage, js_name = getItem)]
+    fn ls_try_get_item(key: &str) -> Result<Option<String>, JsValue>;
+
+    #[wasm_bindgen(catch, js_namespace = localStorage, js_name = setItem)]
+    fn ls_try_set_item(key: &str, value: &str) -> Result<(), JsValue>;
+
+    #[wasm_bindgen(catch, js_namespace = localStorage, js_name = removeItem)]
+    fn ls_try_remove_item(key: &str) -> Result<(), JsValue>;
+
     // Timestamp function
     #[wasm_bindgen(js_name = "Date.now")]
     pub fn date_now() -> f64;
@@ -32,6 +42,18 @@ pub fn current_timestamp() -> u64 {
     (date_now() / 1000.0) as u64
 }
 
+/// Returns whether localStorage can actually be written and read back.
+/// Private browsing or disabled storage makes these calls throw.
+pub fn localstorage_available() -> bool {
+    const SENTINEL: &str = "__tinykv_probe__";
+
+    let round_trip = ls_try_set_item(SENTINEL, SENTINEL)
+        .and_then(|_| ls_try_get_item(SENTINEL))
+        .is_ok_and(|value| value.as_deref() == Some(SENTINEL));
+    let _ = ls_try_remove_item(SENTINEL);
+    round_trip
+}
+
 /// Web storage backend types for WASM environments.
 /// Supports both localStorage storage.
 #[derive(Debug, Clone)]